@@ -1,10 +1,13 @@
-#[cfg(not(target_arch = "wasm32"))]
-use std::sync::atomic::Ordering;
 use std::{
   borrow::Cow,
   collections::HashMap,
   path::{Path, PathBuf},
-  sync::Arc,
+  sync::{atomic::Ordering, Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+  sync::{Mutex, TryLockError},
+  thread::ThreadId,
 };
 
 use napi::{
@@ -14,12 +17,18 @@ use napi::{
 use napi_derive::napi;
 
 #[cfg(not(target_arch = "wasm32"))]
-use parcel_resolver::OsFileSystem;
+use crossbeam_channel::Sender;
+#[cfg(not(target_arch = "wasm32"))]
+use napi::threadsafe_function::{
+  ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
 use parcel_resolver::{
-  ExportsCondition, Extensions, Fields, FileCreateInvalidation, FileSystem,
+  ExportsCondition, Extensions, Fields, FileCreateInvalidation, FileSystem, FileSystemEvent,
   FileSystemRealPathCache, Flags, IncludeNodeModules, Invalidations, ModuleType, Resolution,
-  ResolverError, SpecifierType,
+  ResolverError, SideEffectsResolution, SpecifierType, TraceEvent,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use parcel_resolver::{OsFileSystem, PnpFileSystem};
 
 type NapiSideEffectsVariants = Either3<bool, Vec<String>, HashMap<String, bool>>;
 
@@ -43,6 +52,13 @@ pub struct JsResolverOptions {
   pub extensions: Option<Vec<String>>,
   pub package_exports: bool,
   pub typescript: Option<bool>,
+  pub pnp: Option<bool>,
+  pub case_sensitive: Option<bool>,
+  pub import_map: Option<String>,
+  pub workspaces: Option<bool>,
+  pub preserve_symlinks: Option<bool>,
+  pub inherit_side_effects: Option<bool>,
+  pub inherit_aliases: Option<bool>,
 }
 
 pub struct FunctionRef {
@@ -50,7 +66,7 @@ pub struct FunctionRef {
   reference: Ref<()>,
 }
 
-// We don't currently call functions from multiple threads, but we'll need to change this when we do.
+// Functions are only called from the JS thread. Other threads go through ThreadsafeJsFileSystem.
 unsafe impl Send for FunctionRef {}
 unsafe impl Sync for FunctionRef {}
 
@@ -132,12 +148,168 @@ impl FileSystem for JsFileSystem {
   }
 }
 
+/// A file system request sent from another thread to the JS thread.
+#[cfg(not(target_arch = "wasm32"))]
+enum FsRequest {
+  Canonicalize(PathBuf, Sender<std::io::Result<PathBuf>>),
+  Read(PathBuf, Sender<std::io::Result<String>>),
+  IsFile(PathBuf, Sender<bool>),
+  IsDir(PathBuf, Sender<bool>),
+}
+
+/// Wraps a JsFileSystem so it can be used from any thread. Calls on the JS thread
+/// are made directly. Calls from other threads are queued, and the JS thread handles
+/// all queued requests in a single batch, while the calling threads block on the result.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ThreadsafeJsFileSystem {
+  fs: Arc<JsFileSystem>,
+  js_thread: ThreadId,
+  queue: Arc<Mutex<Vec<FsRequest>>>,
+  flush: ThreadsafeFunction<()>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ThreadsafeJsFileSystem {
+  fn new(env: Env, fs: JsFileSystem) -> napi::Result<Self> {
+    let fs = Arc::new(fs);
+    let queue: Arc<Mutex<Vec<FsRequest>>> = Default::default();
+    let noop = env.create_function_from_closure("flush", |ctx| ctx.env.get_undefined())?;
+    let mut flush = env.create_threadsafe_function(&noop, 0, {
+      let fs = fs.clone();
+      let queue = queue.clone();
+      move |_: ThreadSafeCallContext<()>| {
+        handle_requests(&fs, &queue);
+        Ok(Vec::<JsUnknown>::new())
+      }
+    })?;
+
+    // Don't keep the process alive just because a resolver exists.
+    flush.unref(&env)?;
+
+    Ok(Self {
+      fs,
+      js_thread: std::thread::current().id(),
+      queue,
+      flush,
+    })
+  }
+
+  /// Sends a request to the JS thread, and blocks until it responds.
+  /// Returns None if the request could not be scheduled.
+  fn call<T>(&self, request: impl FnOnce(Sender<T>) -> FsRequest) -> Option<T> {
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    let is_first = {
+      let mut queue = self.queue.lock().unwrap();
+      queue.push(request(tx));
+      queue.len() == 1
+    };
+
+    // Only the first request in a batch schedules a flush. Requests that are queued
+    // before the JS thread gets to it are handled by the same flush.
+    if is_first
+      && self
+        .flush
+        .call(Ok(()), ThreadsafeFunctionCallMode::NonBlocking)
+        != napi::Status::Ok
+    {
+      // Drop the queued senders so all waiting threads fail rather than block forever.
+      self.queue.lock().unwrap().clear();
+    }
+
+    rx.recv().ok()
+  }
+
+  fn is_js_thread(&self) -> bool {
+    std::thread::current().id() == self.js_thread
+  }
+
+  /// Handles the queued requests immediately. Must be called on the JS thread, e.g. while
+  /// it waits for other threads that may be blocked on the file system.
+  fn handle_queued_requests(&self) {
+    debug_assert!(self.is_js_thread());
+    handle_requests(&self.fs, &self.queue);
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn handle_requests(fs: &JsFileSystem, queue: &Mutex<Vec<FsRequest>>) {
+  let requests = std::mem::take(&mut *queue.lock().unwrap());
+  for request in requests {
+    // Errors only occur if the calling thread stopped waiting, so they are ignored.
+    let _ = match request {
+      FsRequest::Canonicalize(path, tx) => {
+        tx.send(fs.canonicalize(&path, &Default::default())).is_ok()
+      }
+      FsRequest::Read(path, tx) => tx.send(fs.read_to_string(&path)).is_ok(),
+      FsRequest::IsFile(path, tx) => tx.send(fs.is_file(&path)).is_ok(),
+      FsRequest::IsDir(path, tx) => tx.send(fs.is_dir(&path)).is_ok(),
+    };
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileSystem for ThreadsafeJsFileSystem {
+  fn canonicalize(
+    &self,
+    path: &Path,
+    cache: &FileSystemRealPathCache,
+  ) -> std::io::Result<std::path::PathBuf> {
+    if self.is_js_thread() {
+      return self.fs.canonicalize(path, cache);
+    }
+
+    self
+      .call(|tx| FsRequest::Canonicalize(path.to_owned(), tx))
+      .unwrap_or_else(|| Err(disconnected()))
+  }
+
+  fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+    if self.is_js_thread() {
+      return self.fs.read_to_string(path);
+    }
+
+    self
+      .call(|tx| FsRequest::Read(path.to_owned(), tx))
+      .unwrap_or_else(|| Err(disconnected()))
+  }
+
+  fn is_file(&self, path: &Path) -> bool {
+    if self.is_js_thread() {
+      return self.fs.is_file(path);
+    }
+
+    self
+      .call(|tx| FsRequest::IsFile(path.to_owned(), tx))
+      .unwrap_or(false)
+  }
+
+  fn is_dir(&self, path: &Path) -> bool {
+    if self.is_js_thread() {
+      return self.fs.is_dir(path);
+    }
+
+    self
+      .call(|tx| FsRequest::IsDir(path.to_owned(), tx))
+      .unwrap_or(false)
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn disconnected() -> std::io::Error {
+  std::io::Error::new(
+    std::io::ErrorKind::BrokenPipe,
+    "The JS file system is no longer available",
+  )
+}
+
 #[napi(object)]
 pub struct ResolveOptions {
   pub filename: String,
   pub specifier_type: String,
   pub parent: String,
   pub package_conditions: Option<Vec<String>>,
+  pub trace: Option<bool>,
+  pub explain_side_effects: Option<bool>,
 }
 
 #[napi(object)]
@@ -162,10 +334,40 @@ pub struct ResolveResult {
   pub invalidate_on_file_change: Vec<String>,
   pub invalidate_on_file_create:
     Vec<Either3<FilePathCreateInvalidation, FileNameCreateInvalidation, GlobCreateInvalidation>>,
+  pub invalidate_on_startup: bool,
+  pub query: Option<String>,
+  pub side_effects: bool,
+  pub side_effects_explanation: JsUnknown,
+  pub error: JsUnknown,
+  pub module_type: u8,
+  pub trace: JsUnknown,
+}
+
+#[napi(object)]
+pub struct BatchResolveResult {
+  pub resolution: JsUnknown,
+  pub invalidate_on_startup: bool,
   pub query: Option<String>,
   pub side_effects: bool,
+  pub side_effects_explanation: JsUnknown,
   pub error: JsUnknown,
   pub module_type: u8,
+  pub trace: JsUnknown,
+}
+
+#[napi(object)]
+pub struct ResolveManyResult {
+  pub results: Vec<BatchResolveResult>,
+  pub invalidate_on_file_change: Vec<String>,
+  pub invalidate_on_file_create:
+    Vec<Either3<FilePathCreateInvalidation, FileNameCreateInvalidation, GlobCreateInvalidation>>,
+}
+
+#[napi(object)]
+pub struct JsFileSystemEvent {
+  pub path: String,
+  #[napi(js_name = "type")]
+  pub event_type: String,
 }
 
 #[napi(object)]
@@ -179,27 +381,38 @@ pub struct JsInvalidations {
 #[napi]
 pub struct Resolver {
   mode: u8,
-  resolver: parcel_resolver::Resolver<'static>,
+  /// Resolutions on other threads hold a read lock, so that the cache is never
+  /// invalidated while they borrow from it.
+  resolver: RwLock<parcel_resolver::Resolver<'static>>,
+  #[cfg(not(target_arch = "wasm32"))]
+  js_fs: Option<Arc<ThreadsafeJsFileSystem>>,
   #[cfg(not(target_arch = "wasm32"))]
   invalidations_cache: parcel_dev_dep_resolver::Cache,
-  supports_async: bool,
 }
 
 #[napi]
 impl Resolver {
   #[napi(constructor)]
   pub fn new(project_root: String, options: JsResolverOptions, env: Env) -> Result<Self> {
-    let mut supports_async = false;
     #[cfg(not(target_arch = "wasm32"))]
-    let fs: Arc<dyn FileSystem> = if let Some(fs) = options.fs {
-      Arc::new(JsFileSystem {
-        canonicalize: FunctionRef::new(env, fs.canonicalize)?,
-        read: FunctionRef::new(env, fs.read)?,
-        is_file: FunctionRef::new(env, fs.is_file)?,
-        is_dir: FunctionRef::new(env, fs.is_dir)?,
-      })
+    let js_fs = match options.fs {
+      Some(fs) => Some(Arc::new(ThreadsafeJsFileSystem::new(
+        env,
+        JsFileSystem {
+          canonicalize: FunctionRef::new(env, fs.canonicalize)?,
+          read: FunctionRef::new(env, fs.read)?,
+          is_file: FunctionRef::new(env, fs.is_file)?,
+          is_dir: FunctionRef::new(env, fs.is_dir)?,
+        },
+      )?)),
+      None => None,
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let fs: Arc<dyn FileSystem> = if let Some(fs) = &js_fs {
+      fs.clone()
+    } else if matches!(options.pnp, Some(true)) {
+      Arc::new(PnpFileSystem::default())
     } else {
-      supports_async = true;
       Arc::new(OsFileSystem)
     };
     #[cfg(target_arch = "wasm32")]
@@ -251,6 +464,46 @@ impl Resolver {
       resolver.flags |= Flags::TYPESCRIPT;
     }
 
+    if matches!(options.pnp, Some(true)) {
+      resolver.flags |= Flags::PNP;
+    }
+
+    if matches!(options.case_sensitive, Some(true)) {
+      resolver.flags |= Flags::CASE_SENSITIVE;
+    }
+
+    if matches!(options.workspaces, Some(true)) {
+      resolver.flags |= Flags::WORKSPACES;
+    }
+
+    if matches!(options.preserve_symlinks, Some(true)) {
+      resolver.flags |= Flags::PRESERVE_SYMLINKS;
+    }
+
+    if matches!(options.inherit_side_effects, Some(true)) {
+      resolver.flags |= Flags::INHERIT_SIDE_EFFECTS;
+    }
+
+    if matches!(options.inherit_aliases, Some(true)) {
+      resolver.flags |= Flags::INHERIT_ALIASES;
+    }
+
+    if let Some(import_map) = options.import_map {
+      // The import map is read through the cache, so that it is read again when it changes.
+      // Read it here too, so that errors are reported when the resolver is created.
+      let path = PathBuf::from(import_map);
+      resolver.cache.read_import_map(&path).map_err(|err| {
+        let message = match err {
+          ResolverError::JsonError(err) => {
+            format!("Invalid import map {}: {}", path.display(), err.message)
+          }
+          _ => format!("Could not read import map {}", path.display()),
+        };
+        napi::Error::new(napi::Status::InvalidArg, message)
+      })?;
+      resolver.import_map = Some(path);
+    }
+
     if let Some(module_dir_resolver) = options.module_dir_resolver {
       let module_dir_resolver = FunctionRef::new(env, module_dir_resolver)?;
       resolver.module_dir_resolver = Some(Arc::new(move |module: &str, from: &Path| {
@@ -266,24 +519,67 @@ impl Resolver {
         let r = call(module);
         r.map_err(|_| ResolverError::ModuleNotFound {
           module: module.to_owned(),
+          suggestions: Vec::new(),
         })
       }));
     }
 
     Ok(Self {
       mode: options.mode,
-      resolver,
-      supports_async,
+      resolver: RwLock::new(resolver),
+      #[cfg(not(target_arch = "wasm32"))]
+      js_fs,
       #[cfg(not(target_arch = "wasm32"))]
       invalidations_cache: Default::default(),
     })
   }
 
+  fn read_resolver(&self) -> RwLockReadGuard<'_, parcel_resolver::Resolver<'static>> {
+    self.resolver.read().unwrap()
+  }
+
+  #[cfg(target_arch = "wasm32")]
+  fn write_resolver(&self) -> RwLockWriteGuard<'_, parcel_resolver::Resolver<'static>> {
+    self.resolver.write().unwrap()
+  }
+
+  /// Waits until no resolutions are running, and locks the resolver. This is called on the JS thread,
+  /// so requests from resolutions that are blocked on the JS file system are handled while waiting.
+  #[cfg(not(target_arch = "wasm32"))]
+  fn write_resolver(&self) -> RwLockWriteGuard<'_, parcel_resolver::Resolver<'static>> {
+    loop {
+      match self.resolver.try_write() {
+        Ok(resolver) => return resolver,
+        Err(TryLockError::Poisoned(err)) => return err.into_inner(),
+        Err(TryLockError::WouldBlock) => {
+          if let Some(fs) = &self.js_fs {
+            fs.handle_queued_requests();
+          }
+          std::thread::yield_now();
+        }
+      }
+    }
+  }
+
   fn resolve_internal(
     &self,
     options: ResolveOptions,
-  ) -> napi::Result<(parcel_resolver::ResolveResult, bool, u8)> {
-    let mut res = self.resolver.resolve_with_options(
+  ) -> napi::Result<(
+    parcel_resolver::ResolveResult,
+    bool,
+    Option<SideEffectsResolution>,
+    u8,
+  )> {
+    let mut resolve_options = if let Some(conditions) = options.package_conditions {
+      get_resolve_options(conditions)
+    } else {
+      Default::default()
+    };
+    resolve_options.trace = matches!(options.trace, Some(true));
+    let explain_side_effects = matches!(options.explain_side_effects, Some(true));
+
+    let resolver = self.read_resolver();
+    let mut res = resolver.resolve_with_options(
       &options.filename,
       Path::new(&options.parent),
       match options.specifier_type.as_ref() {
@@ -303,16 +599,19 @@ impl Resolver {
           ))
         }
       },
-      if let Some(conditions) = options.package_conditions {
-        get_resolve_options(conditions)
-      } else {
-        Default::default()
-      },
+      resolve_options,
     );
 
+    let mut side_effects_explanation = None;
     let side_effects = if let Ok((Resolution::Path(p), _)) = &res.result {
-      match self.resolver.resolve_side_effects(p, &res.invalidations) {
-        Ok(side_effects) => side_effects,
+      match resolver.resolve_side_effects(p, &res.invalidations) {
+        Ok(resolution) => {
+          let side_effects = resolution.side_effects;
+          if explain_side_effects {
+            side_effects_explanation = Some(resolution);
+          }
+          side_effects
+        }
         Err(err) => {
           res.result = Err(err);
           true
@@ -326,7 +625,7 @@ impl Resolver {
 
     if self.mode == 2 {
       if let Ok((Resolution::Path(p), _)) = &res.result {
-        module_type = match self.resolver.resolve_module_type(p, &res.invalidations) {
+        module_type = match resolver.resolve_module_type(p, &res.invalidations) {
           Ok(t) => match t {
             ModuleType::CommonJs | ModuleType::Json => 1,
             ModuleType::Module => 2,
@@ -339,7 +638,7 @@ impl Resolver {
       }
     }
 
-    Ok((res, side_effects, module_type))
+    Ok((res, side_effects, side_effects_explanation, module_type))
   }
 
   fn resolve_result_to_js(
@@ -347,37 +646,57 @@ impl Resolver {
     env: Env,
     res: parcel_resolver::ResolveResult,
     side_effects: bool,
+    side_effects_explanation: Option<SideEffectsResolution>,
     module_type: u8,
   ) -> napi::Result<ResolveResult> {
+    let invalidate_on_startup = res
+      .invalidations
+      .invalidate_on_startup
+      .load(Ordering::Relaxed);
     let (invalidate_on_file_change, invalidate_on_file_create) =
       convert_invalidations(res.invalidations);
+    let trace = trace_to_js(env, &res.trace)?;
+    let side_effects_explanation = side_effects_explanation_to_js(env, side_effects_explanation)?;
 
     match res.result {
       Ok((res, query)) => Ok(ResolveResult {
         resolution: env.to_js_value(&res)?,
         invalidate_on_file_change,
         invalidate_on_file_create,
+        invalidate_on_startup,
         side_effects,
+        side_effects_explanation,
         query,
         error: env.get_undefined()?.into_unknown(),
         module_type,
+        trace,
       }),
       Err(err) => Ok(ResolveResult {
         resolution: env.get_undefined()?.into_unknown(),
         invalidate_on_file_change,
         invalidate_on_file_create,
+        invalidate_on_startup,
         side_effects: true,
+        side_effects_explanation,
         query: None,
         error: env.to_js_value(&err)?,
         module_type: 0,
+        trace,
       }),
     }
   }
 
   #[napi]
   pub fn resolve(&self, options: ResolveOptions, env: Env) -> Result<ResolveResult> {
-    let (res, side_effects, module_type) = self.resolve_internal(options)?;
-    self.resolve_result_to_js(env, res, side_effects, module_type)
+    let (res, side_effects, side_effects_explanation, module_type) =
+      self.resolve_internal(options)?;
+    self.resolve_result_to_js(
+      env,
+      res,
+      side_effects,
+      side_effects_explanation,
+      module_type,
+    )
   }
 
   #[cfg(target_arch = "wasm32")]
@@ -390,27 +709,189 @@ impl Resolver {
   #[napi]
   pub fn resolve_async(&'static self, options: ResolveOptions, env: Env) -> Result<JsObject> {
     let (deferred, promise) = env.create_deferred()?;
-    let resolver = &self.resolver;
 
-    if !self.supports_async || resolver.module_dir_resolver.is_some() {
+    if self.read_resolver().module_dir_resolver.is_some() {
+      return Err(napi::Error::new(
+        napi::Status::GenericFailure,
+        "resolveAsync does not support module_dir_resolver",
+      ));
+    }
+
+    rayon::spawn(move || {
+      let (res, side_effects, side_effects_explanation, module_type) =
+        match self.resolve_internal(options) {
+          Ok(r) => r,
+          Err(e) => return deferred.reject(e),
+        };
+
+      deferred.resolve(move |env| {
+        self.resolve_result_to_js(
+          env,
+          res,
+          side_effects,
+          side_effects_explanation,
+          module_type,
+        )
+      });
+    });
+
+    Ok(promise)
+  }
+
+  #[cfg(target_arch = "wasm32")]
+  #[napi]
+  pub fn resolve_many(&'static self) -> Result<JsObject> {
+    panic!("resolveMany() is not supported in Wasm builds")
+  }
+
+  /// Resolves a batch of requests in parallel, e.g. all dependencies of an asset.
+  /// Invalidations are merged across all requests rather than returned for each one.
+  #[cfg(not(target_arch = "wasm32"))]
+  #[napi]
+  pub fn resolve_many(&'static self, options: Vec<ResolveOptions>, env: Env) -> Result<JsObject> {
+    use rayon::prelude::*;
+
+    let (deferred, promise) = env.create_deferred()?;
+
+    if self.read_resolver().module_dir_resolver.is_some() {
       return Err(napi::Error::new(
         napi::Status::GenericFailure,
-        "resolveAsync does not support custom fs or module_dir_resolver",
+        "resolveMany does not support module_dir_resolver",
       ));
     }
 
     rayon::spawn(move || {
-      let (res, side_effects, module_type) = match self.resolve_internal(options) {
+      let results = match options
+        .into_par_iter()
+        .map(|options| self.resolve_internal(options))
+        .collect::<Result<Vec<_>>>()
+      {
         Ok(r) => r,
         Err(e) => return deferred.reject(e),
       };
 
-      deferred.resolve(move |env| self.resolve_result_to_js(env, res, side_effects, module_type));
+      let invalidations = Invalidations::default();
+      let results = results
+        .into_iter()
+        .map(
+          |(res, side_effects, side_effects_explanation, module_type)| {
+            invalidations.extend(&res.invalidations);
+            (
+              res.result,
+              res
+                .invalidations
+                .invalidate_on_startup
+                .load(Ordering::Relaxed),
+              res.trace,
+              side_effects,
+              side_effects_explanation,
+              module_type,
+            )
+          },
+        )
+        .collect::<Vec<_>>();
+
+      deferred.resolve(move |env| {
+        let (invalidate_on_file_change, invalidate_on_file_create) =
+          convert_invalidations(invalidations);
+
+        let results = results
+          .into_iter()
+          .map(
+            |(
+              result,
+              invalidate_on_startup,
+              trace,
+              side_effects,
+              side_effects_explanation,
+              module_type,
+            )| {
+              let trace = trace_to_js(env, &trace)?;
+              let side_effects_explanation =
+                side_effects_explanation_to_js(env, side_effects_explanation)?;
+              Ok(match result {
+                Ok((res, query)) => BatchResolveResult {
+                  resolution: env.to_js_value(&res)?,
+                  invalidate_on_startup,
+                  query,
+                  side_effects,
+                  side_effects_explanation,
+                  error: env.get_undefined()?.into_unknown(),
+                  module_type,
+                  trace,
+                },
+                Err(err) => BatchResolveResult {
+                  resolution: env.get_undefined()?.into_unknown(),
+                  invalidate_on_startup,
+                  query: None,
+                  side_effects: true,
+                  side_effects_explanation,
+                  error: env.to_js_value(&err)?,
+                  module_type: 0,
+                  trace,
+                },
+              })
+            },
+          )
+          .collect::<Result<Vec<_>>>()?;
+
+        Ok(ResolveManyResult {
+          results,
+          invalidate_on_file_change,
+          invalidate_on_file_create,
+        })
+      });
     });
 
     Ok(promise)
   }
 
+  #[napi]
+  pub fn invalidate(&self, path: String) {
+    if let Some(cache) = self.write_resolver().cache.as_mut() {
+      cache.invalidate(Path::new(&path));
+    }
+  }
+
+  #[napi]
+  pub fn invalidate_events(&self, events: Vec<JsFileSystemEvent>) -> Result<()> {
+    let events = events
+      .into_iter()
+      .map(|event| {
+        let path = PathBuf::from(event.path);
+        Ok(match event.event_type.as_ref() {
+          "create" => FileSystemEvent::Create(path),
+          "update" => FileSystemEvent::Update(path),
+          "delete" => FileSystemEvent::Delete(path),
+          _ => {
+            return Err(napi::Error::new(
+              napi::Status::InvalidArg,
+              format!("Invalid event type: {}", event.event_type),
+            ))
+          }
+        })
+      })
+      .collect::<Result<Vec<_>>>()?;
+
+    if let Some(cache) = self.write_resolver().cache.as_mut() {
+      cache.invalidate_events(&events);
+    }
+
+    Ok(())
+  }
+
+  #[napi]
+  pub fn validate_package_exports(&self, path: String, env: Env) -> Result<JsUnknown> {
+    let path = Path::new(&path);
+    match self.read_resolver().cache.fs.read_to_string(path) {
+      Ok(contents) => env.to_js_value(&parcel_resolver::validate_exports(path, &contents)),
+      Err(err) => Err(napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("Failed to read {}: {}", path.display(), err),
+      )),
+    }
+  }
+
   #[cfg(target_arch = "wasm32")]
   #[napi]
   pub fn get_invalidations(&self, _path: String) -> napi::Result<JsInvalidations> {
@@ -421,10 +902,11 @@ impl Resolver {
   #[napi]
   pub fn get_invalidations(&self, path: String) -> napi::Result<JsInvalidations> {
     let path = Path::new(&path);
+    let resolver = self.read_resolver();
     match parcel_dev_dep_resolver::build_esm_graph(
       path,
-      &self.resolver.project_root,
-      &self.resolver.cache,
+      &resolver.project_root,
+      &resolver.cache,
       &self.invalidations_cache,
     ) {
       Ok(invalidations) => {
@@ -445,6 +927,24 @@ impl Resolver {
   }
 }
 
+fn trace_to_js(env: Env, trace: &[TraceEvent]) -> napi::Result<JsUnknown> {
+  if trace.is_empty() {
+    Ok(env.get_undefined()?.into_unknown())
+  } else {
+    env.to_js_value(&trace)
+  }
+}
+
+fn side_effects_explanation_to_js(
+  env: Env,
+  explanation: Option<SideEffectsResolution>,
+) -> napi::Result<JsUnknown> {
+  match explanation {
+    Some(explanation) => env.to_js_value(&explanation),
+    None => Ok(env.get_undefined()?.into_unknown()),
+  }
+}
+
 fn convert_invalidations(
   invalidations: Invalidations,
 ) -> (
@@ -489,5 +989,6 @@ fn get_resolve_options(mut custom_conditions: Vec<String>) -> parcel_resolver::R
   parcel_resolver::ResolveOptions {
     conditions,
     custom_conditions,
+    ..Default::default()
   }
 }
@@ -121,6 +121,10 @@ async function run({input, api, options}): Promise<PathRequestResult> {
     }
   }
 
+  if (result.invalidateOnStartup) {
+    api.invalidateOnStartup();
+  }
+
   for (let config of resolverRunner.configs.values()) {
     await runConfigRequest(api, config);
   }
@@ -153,6 +157,7 @@ type ResolverResult = {|
   invalidateOnFileCreate?: Array<FileCreateInvalidation>,
   invalidateOnFileChange?: Array<FilePath>,
   invalidateOnEnvChange?: Array<string>,
+  invalidateOnStartup?: boolean,
   diagnostics?: Array<Diagnostic>,
 |};
 
@@ -283,6 +288,7 @@ export class ResolverRunner {
     let invalidateOnFileCreate = [];
     let invalidateOnFileChange = [];
     let invalidateOnEnvChange = [];
+    let invalidateOnStartup = false;
     for (let resolver of resolvers) {
       let measurement;
       try {
@@ -330,12 +336,17 @@ export class ResolverRunner {
             invalidateOnFileChange.push(...result.invalidateOnFileChange);
           }
 
+          if (result.invalidateOnStartup) {
+            invalidateOnStartup = true;
+          }
+
           if (result.isExcluded) {
             return {
               assetGroup: null,
               invalidateOnFileCreate,
               invalidateOnFileChange,
               invalidateOnEnvChange,
+              invalidateOnStartup,
             };
           }
 
@@ -367,6 +378,7 @@ export class ResolverRunner {
               invalidateOnFileCreate,
               invalidateOnFileChange,
               invalidateOnEnvChange,
+              invalidateOnStartup,
             };
           }
 
@@ -423,6 +435,7 @@ export class ResolverRunner {
         invalidateOnFileCreate,
         invalidateOnFileChange,
         invalidateOnEnvChange,
+        invalidateOnStartup,
       };
     }
 
@@ -446,6 +459,7 @@ export class ResolverRunner {
       invalidateOnFileCreate,
       invalidateOnFileChange,
       invalidateOnEnvChange,
+      invalidateOnStartup,
       diagnostics,
     };
   }
@@ -273,6 +273,81 @@ describe('hmr', function () {
       assert.equal(message.type, 'update');
     });
 
+    it('should keep the dependencies of a file with recoverable parse errors', async function () {
+      let port = await getPort();
+      let b = bundler(path.join(__dirname, '/input/index.js'), {
+        hmrOptions: {port},
+        inputFS: overlayFS,
+        config,
+      });
+
+      await outputFS.writeFile(
+        path.join(__dirname, '/input/other.js'),
+        'exports.c = 5;',
+      );
+
+      subscription = await b.watch();
+      await getNextBuild(b);
+
+      ws = await openSocket('ws://localhost:' + port);
+
+      // The duplicate constructor is a parse error that the parser recovers from.
+      await outputFS.writeFile(
+        path.join(__dirname, '/input/local.js'),
+        'require("./other");\nclass A { constructor() {} constructor() {} }\nexports.a = 5;',
+      );
+
+      let [event, message] = await Promise.all([
+        getNextBuild(b),
+        nextWSMessage(nullthrows(ws)),
+      ]);
+
+      assert.equal(event.type, 'buildSuccess');
+      let assets = [];
+      let bundle = nullthrows(event.bundleGraph).getBundles()[0];
+      bundle.traverseAssets(asset => {
+        assets.push(path.basename(asset.filePath));
+      });
+      assert(assets.includes('other.js'));
+
+      // The errors are thrown when the asset runs instead.
+      assert.equal(message.type, 'update');
+      assert(
+        message.assets.some(asset =>
+          asset.output.includes('throw new SyntaxError('),
+        ),
+      );
+    });
+
+    it('should fail a file with recoverable parse errors and other errors', async function () {
+      let port = await getPort();
+      let b = bundler(path.join(__dirname, '/input/index.js'), {
+        hmrOptions: {port},
+        inputFS: overlayFS,
+        config,
+      });
+
+      await outputFS.writeFile(
+        path.join(__dirname, '/input/other.js'),
+        'exports.c = 5;',
+      );
+
+      subscription = await b.watch();
+      await getNextBuild(b);
+
+      ws = await openSocket('ws://localhost:' + port);
+
+      // Constructing a worker from a string literal is an error from collecting dependencies,
+      // rather than from parsing, so the asset fails even though it has dependencies.
+      await outputFS.writeFile(
+        path.join(__dirname, '/input/local.js'),
+        'require("./other");\nclass A { constructor() {} constructor() {} }\nnew Worker("worker.js");\nexports.a = 5;',
+      );
+
+      let message = await nextWSMessage(nullthrows(ws));
+      assert.equal(message.type, 'error');
+    });
+
     it('should make a secure connection', async function () {
       let port = await getPort();
       let b = bundler(path.join(__dirname, '/input/index.js'), {
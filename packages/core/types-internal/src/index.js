@@ -1658,6 +1658,8 @@ export type ResolveResult = {|
   +invalidateOnFileCreate?: Array<FileCreateInvalidation>,
   /** A list of files that should invalidate the resolution if modified or deleted. */
   +invalidateOnFileChange?: Array<FilePath>,
+  /** Whether the resolution should be invalidated when Parcel restarts. */
+  +invalidateOnStartup?: boolean,
   /** Invalidates the resolution when the given environment variable changes.*/
   +invalidateOnEnvChange?: Array<string>,
 |};
@@ -0,0 +1,283 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs, io,
+  path::PathBuf,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+};
+
+use parcel_macros::{JsValue, Location, MacroCallback};
+use parking_lot::Mutex;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::{transform, Config, SourceType, TransformResult};
+
+/// Caches the results of `transform`, keyed by a hash of the source code and the rest of the config,
+/// so that unchanged assets are not parsed and transformed again.
+///
+/// This is only used by Rust callers. The napi bindings call `transform` directly, because Parcel
+/// already caches the results of transformers in JS.
+pub struct TransformCache {
+  entries: Mutex<Entries>,
+  capacity: usize,
+  dir: Option<PathBuf>,
+}
+
+/// The results held in memory, along with the order in which they were last used. Each use takes
+/// a new tick, so the first entry in `order` is the least recently used one.
+#[derive(Default)]
+struct Entries {
+  results: HashMap<u64, (TransformResult, u64)>,
+  order: BTreeMap<u64, u64>,
+  tick: u64,
+}
+
+impl Entries {
+  fn get(&mut self, key: u64) -> Option<TransformResult> {
+    let (result, used) = self.results.get_mut(&key)?;
+    self.order.remove(used);
+    self.tick += 1;
+    *used = self.tick;
+    self.order.insert(self.tick, key);
+    Some(result.clone())
+  }
+
+  fn insert(&mut self, key: u64, result: TransformResult, capacity: usize) {
+    self.tick += 1;
+    if let Some((_, used)) = self.results.insert(key, (result, self.tick)) {
+      self.order.remove(&used);
+    }
+    self.order.insert(self.tick, key);
+
+    while self.results.len() > capacity {
+      match self.order.pop_first() {
+        Some((_, oldest)) => self.results.remove(&oldest),
+        None => break,
+      };
+    }
+  }
+}
+
+impl TransformCache {
+  /// Creates an in-memory cache, which holds up to `capacity` results and evicts the least recently
+  /// used first.
+  pub fn new(capacity: usize) -> Self {
+    TransformCache {
+      entries: Mutex::new(Entries::default()),
+      capacity,
+      dir: None,
+    }
+  }
+
+  /// Creates a cache that also stores results in a directory, so that they can be reused by later
+  /// processes. Entries are not invalidated when the transformer itself changes, so the directory
+  /// should be specific to its version.
+  pub fn with_dir(capacity: usize, dir: PathBuf) -> Self {
+    TransformCache {
+      dir: Some(dir),
+      ..TransformCache::new(capacity)
+    }
+  }
+
+  /// Returns the number of results held in memory.
+  pub fn len(&self) -> usize {
+    self.entries.lock().results.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Removes all results held in memory. Results stored in the cache directory are kept.
+  pub fn clear(&self) {
+    *self.entries.lock() = Entries::default();
+  }
+
+  fn get(&self, key: u64) -> Option<TransformResult> {
+    if let Some(result) = self.entries.lock().get(key) {
+      return Some(result);
+    }
+
+    let data = fs::read(self.entry_path(key)?).ok()?;
+    let result: TransformResult = serde_json::from_slice(&data).ok()?;
+    self.insert_in_memory(key, result.clone());
+    Some(result)
+  }
+
+  fn insert(&self, key: u64, result: &TransformResult) {
+    // The cache directory is best effort: if an entry cannot be written, it is simply transformed again.
+    if let (Some(dir), Some(path)) = (&self.dir, self.entry_path(key)) {
+      if let Ok(data) = serde_json::to_vec(result) {
+        // Write to a temporary file first, so that other processes never read a partial entry.
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        let _ = fs::create_dir_all(dir)
+          .and_then(|_| fs::write(&tmp, data))
+          .and_then(|_| fs::rename(&tmp, &path));
+      }
+    }
+
+    self.insert_in_memory(key, result.clone());
+  }
+
+  fn insert_in_memory(&self, key: u64, result: TransformResult) {
+    self.entries.lock().insert(key, result, self.capacity);
+  }
+
+  fn entry_path(&self, key: u64) -> Option<PathBuf> {
+    self
+      .dir
+      .as_ref()
+      .map(|dir| dir.join(format!("{:016x}.json", key)))
+  }
+}
+
+/// Transforms a file like `transform`, but returns the cached result if the same code was transformed
+/// with the same config before. Results that depend on other files, because they may inline
+/// `fs.readFileSync` calls or evaluate macros, are not cached.
+pub fn transform_cached(
+  mut config: Config,
+  call_macro: Option<MacroCallback>,
+  cache: &TransformCache,
+) -> Result<TransformResult, io::Error> {
+  let may_inline_fs = config.inline_fs
+    && config.source_type != SourceType::Script
+    && String::from_utf8_lossy(&config.code).contains("readFileSync");
+  let key = match cache_key(&mut config) {
+    Some(key) if !may_inline_fs => key,
+    _ => return transform(config, call_macro),
+  };
+
+  if let Some(result) = cache.get(key) {
+    return Ok(result);
+  }
+
+  let called_macro = Arc::new(AtomicBool::new(false));
+  let call_macro = call_macro.map(|call_macro| {
+    let called_macro = called_macro.clone();
+    Arc::new(
+      move |src: String, export: String, args: Vec<JsValue>, loc: Location| {
+        called_macro.store(true, Ordering::Relaxed);
+        call_macro(src, export, args, loc)
+      },
+    ) as MacroCallback
+  });
+
+  let result = transform(config, call_macro)?;
+  if !called_macro.load(Ordering::Relaxed) {
+    cache.insert(key, &result);
+  }
+
+  Ok(result)
+}
+
+/// Version of the cache key, which must be changed when the format of `TransformResult` changes.
+const CACHE_KEY_VERSION: &[u8] = b"1";
+
+/// Hashes the source code and the serialized config. The config is converted to a JSON value first,
+/// which sorts the keys of maps such as `env`, so that the key does not depend on their order.
+/// The code is taken out of the config while it is serialized, rather than copied into the JSON value.
+fn cache_key(config: &mut Config) -> Option<u64> {
+  let code = std::mem::take(&mut config.code);
+  let value = serde_json::to_value(&*config);
+  config.code = code;
+
+  let mut hasher = Xxh3::new();
+  hasher.update(CACHE_KEY_VERSION);
+  hasher.update(&(config.code.len() as u64).to_le_bytes());
+  hasher.update(&config.code);
+  hasher.update(&serde_json::to_vec(&value.ok()?).ok()?);
+  Some(hasher.digest())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::make_test_config;
+
+  #[test]
+  fn test_transform_cached() {
+    let cache = TransformCache::new(2);
+    let result = transform_cached(make_test_config("import a from 'a';"), None, &cache).unwrap();
+    assert_eq!(cache.len(), 1);
+
+    let cached = transform_cached(make_test_config("import a from 'a';"), None, &cache).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cached.code, result.code);
+    assert_eq!(cached.dependencies, result.dependencies);
+
+    let mut config = make_test_config("import a from 'a';");
+    config.is_development = true;
+    transform_cached(config, None, &cache).unwrap();
+    assert_eq!(cache.len(), 2);
+
+    // The least recently used result is evicted.
+    transform_cached(make_test_config("import b from 'b';"), None, &cache).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert!(cache
+      .get(cache_key(&mut make_test_config("import a from 'a';")).unwrap())
+      .is_none());
+  }
+
+  #[test]
+  fn test_transform_cache_lru() {
+    let cache = TransformCache::new(2);
+    transform_cached(make_test_config("import a from 'a';"), None, &cache).unwrap();
+    transform_cached(make_test_config("import b from 'b';"), None, &cache).unwrap();
+
+    // Using a result makes it the most recent, so the next insert evicts the other one.
+    transform_cached(make_test_config("import a from 'a';"), None, &cache).unwrap();
+    transform_cached(make_test_config("import c from 'c';"), None, &cache).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert!(cache
+      .get(cache_key(&mut make_test_config("import a from 'a';")).unwrap())
+      .is_some());
+    assert!(cache
+      .get(cache_key(&mut make_test_config("import b from 'b';")).unwrap())
+      .is_none());
+  }
+
+  #[test]
+  fn test_transform_cached_inline_fs() {
+    let cache = TransformCache::new(10);
+    let mut config =
+      make_test_config("import fs from 'fs'; fs.readFileSync(__dirname + '/foo.txt');");
+    config.inline_fs = true;
+    transform_cached(config, None, &cache).unwrap();
+    assert!(cache.is_empty());
+  }
+
+  #[test]
+  fn test_cache_key() {
+    let mut a = make_test_config("foo");
+    let mut b = make_test_config("foo");
+    for (key, value) in [("A", "1"), ("B", "2"), ("C", "3"), ("D", "4")] {
+      a.env.insert(key.into(), value.into());
+    }
+    for (key, value) in [("D", "4"), ("C", "3"), ("B", "2"), ("A", "1")] {
+      b.env.insert(key.into(), value.into());
+    }
+    assert_eq!(cache_key(&mut a), cache_key(&mut b));
+    assert_eq!(a.code, b"foo");
+
+    b.code = b"bar".to_vec();
+    assert_ne!(cache_key(&mut a), cache_key(&mut b));
+  }
+
+  #[test]
+  fn test_cache_dir() {
+    let dir = std::env::temp_dir().join(format!("parcel-transform-cache-{}", std::process::id()));
+    let cache = TransformCache::with_dir(10, dir.clone());
+    let result = transform_cached(make_test_config("export const a = 1;"), None, &cache).unwrap();
+
+    // A new cache reads the result stored by the previous one.
+    let cache = TransformCache::with_dir(10, dir.clone());
+    let key = cache_key(&mut make_test_config("export const a = 1;")).unwrap();
+    let cached = cache.get(key).unwrap();
+    assert_eq!(cached.code, result.code);
+    assert_eq!(cache.len(), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -65,6 +65,8 @@ pub struct Collect {
   pub has_cjs_exports: bool,
   pub is_esm: bool,
   pub should_wrap: bool,
+  /// whether the module calls the global `eval`
+  pub has_eval: bool,
   /// local variable binding -> descriptor
   pub imports: HashMap<Id, Import>,
   pub this_exprs: HashMap<JsWord, Span>,
@@ -90,7 +92,7 @@ pub struct Collect {
   is_module: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct CollectImportedSymbol {
   pub source: JsWord,
@@ -100,7 +102,7 @@ pub struct CollectImportedSymbol {
   pub kind: ImportKind,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct CollectExportedSymbol {
   pub source: Option<JsWord>,
@@ -109,13 +111,13 @@ pub struct CollectExportedSymbol {
   pub loc: SourceLocation,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectExportedAll {
   pub source: JsWord,
   pub loc: SourceLocation,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[non_exhaustive]
 pub struct CollectResult {
   pub imports: Vec<CollectImportedSymbol>,
@@ -145,6 +147,7 @@ impl Collect {
       has_cjs_exports: false,
       is_esm: false,
       should_wrap: false,
+      has_eval: false,
       imports: HashMap::new(),
       this_exprs: HashMap::new(),
       exports: HashMap::new(),
@@ -924,6 +927,7 @@ impl Visit for Collect {
         Expr::Ident(ident) => {
           if ident.sym == js_word!("eval") && is_unresolved(&ident, self.unresolved_mark) {
             self.should_wrap = true;
+            self.has_eval = true;
             self.add_bailout(node.span, BailoutReason::Eval);
           }
         }
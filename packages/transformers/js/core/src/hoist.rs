@@ -54,7 +54,7 @@ pub fn hoist(
 ///
 /// When a file exports a symbol, parcel will rewrite it as a mangled
 /// export identifier.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportedSymbol {
   /// The mangled name the transformer has generated and replaced the variable
   /// uses with
@@ -83,7 +83,7 @@ pub struct ExportedSymbol {
 /// * `loc` will be this source-code location
 ///
 /// See [`HoistResult::imported_symbols`] and [`HoistResult::re_exports`].
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportedSymbol {
   /// The specifier for a certain dependency this symbol comes from
   pub source: JsWord,
@@ -122,7 +122,7 @@ struct Hoist<'a> {
 
 /// Data pertaining to mangled identifiers replacing import and export statements
 /// on transformed files.
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct HoistResult {
   /// A vector of the symbols imported from other files.
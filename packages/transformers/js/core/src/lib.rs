@@ -1,3 +1,4 @@
+mod cache;
 mod collect;
 mod constant_module;
 mod dependency_collector;
@@ -5,6 +6,7 @@ mod env_replacer;
 mod fs;
 mod global_replacer;
 mod hoist;
+mod minify;
 mod modules;
 mod node_replacer;
 #[cfg(test)]
@@ -18,6 +20,7 @@ use std::{
   str::FromStr,
 };
 
+pub use cache::{transform_cached, TransformCache};
 pub use collect::CollectImportedSymbol;
 use collect::{Collect, CollectResult};
 use constant_module::ConstantModule;
@@ -28,6 +31,7 @@ use global_replacer::GlobalReplacer;
 use hoist::{hoist, HoistResult};
 pub use hoist::{ExportedSymbol, ImportedSymbol};
 use indexmap::IndexMap;
+use minify::minify;
 use modules::esm2cjs;
 use node_replacer::NodeReplacer;
 use parcel_macros::{MacroCallback, MacroError, Macros};
@@ -36,13 +40,13 @@ use serde::{Deserialize, Serialize};
 use swc_core::{
   common::{
     chain, comments::SingleThreadedComments, errors::Handler, pass::Optional,
-    source_map::SourceMapGenConfig, sync::Lrc, FileName, Globals, Mark, SourceMap,
+    source_map::SourceMapGenConfig, sourcemap, sync::Lrc, FileName, Globals, Mark, SourceMap,
   },
   ecma::{
-    ast::{Module, ModuleItem, Program},
+    ast::{EsVersion, Module, ModuleItem, Program},
     codegen::text_writer::JsWriter,
     parser::{error::Error, lexer::Lexer, EsSyntax, Parser, StringInput, Syntax, TsSyntax},
-    preset_env::{preset_env, Mode::Entry, Targets, Version, Versions},
+    preset_env::{preset_env, Feature, Mode::Entry, Targets, Version, Versions},
     transforms::{
       base::{
         fixer::{fixer, paren_remover},
@@ -66,7 +70,7 @@ pub use utils::{SourceLocation, SourceType};
 
 type SourceMapBuffer = Vec<(swc_core::common::BytePos, swc_core::common::LineCol)>;
 
-#[derive(Default, Serialize, Debug, Deserialize)]
+#[derive(Serialize, Debug, Deserialize)]
 pub struct Config {
   pub filename: String,
   #[serde(with = "serde_bytes")]
@@ -91,7 +95,17 @@ pub struct Config {
   pub is_development: bool,
   pub react_refresh: bool,
   pub targets: Option<HashMap<String, String>>,
+  /// The ECMAScript version to generate code for. Defaults to the newest version supported by all
+  /// of the targets, or ES5 if there are none.
+  pub output_target: Option<EsVersion>,
+  /// Escape non-ASCII characters, so that the output works regardless of the encoding it is loaded with.
+  /// Enabled unless set to false.
+  #[serde(default = "default_ascii_only")]
+  pub ascii_only: bool,
   pub source_maps: bool,
+  /// A source map from a previous transformation of the code, e.g. by a compile-to-JS language.
+  /// The output source map is composed with it, so that it points to the original sources.
+  pub input_source_map: Option<String>,
   pub scope_hoist: bool,
   pub source_type: SourceType,
   pub supports_module_workers: bool,
@@ -101,9 +115,63 @@ pub struct Config {
   pub is_swc_helpers: bool,
   pub standalone: bool,
   pub inline_constants: bool,
+  /// Compress and mangle the output. This only applies when not scope hoisting, since hoisted
+  /// assets are minified once they are concatenated into a bundle.
+  pub minify: bool,
+  /// Continue transforming the AST recovered from recoverable parse errors, so that dependencies are
+  /// still collected. The parse errors are reported as diagnostics, and no code is generated.
+  pub recover_parse_errors: bool,
 }
 
-#[derive(Serialize, Debug, Default)]
+impl Default for Config {
+  fn default() -> Self {
+    Config {
+      filename: Default::default(),
+      code: Default::default(),
+      module_id: Default::default(),
+      project_root: Default::default(),
+      replace_env: Default::default(),
+      env: Default::default(),
+      inline_fs: Default::default(),
+      insert_node_globals: Default::default(),
+      node_replacer: Default::default(),
+      is_browser: Default::default(),
+      is_worker: Default::default(),
+      is_type_script: Default::default(),
+      is_jsx: Default::default(),
+      jsx_pragma: Default::default(),
+      jsx_pragma_frag: Default::default(),
+      automatic_jsx_runtime: Default::default(),
+      jsx_import_source: Default::default(),
+      decorators: Default::default(),
+      use_define_for_class_fields: Default::default(),
+      is_development: Default::default(),
+      react_refresh: Default::default(),
+      targets: Default::default(),
+      output_target: Default::default(),
+      ascii_only: true,
+      source_maps: Default::default(),
+      input_source_map: Default::default(),
+      scope_hoist: Default::default(),
+      source_type: Default::default(),
+      supports_module_workers: Default::default(),
+      is_library: Default::default(),
+      is_esm_output: Default::default(),
+      trace_bailouts: Default::default(),
+      is_swc_helpers: Default::default(),
+      standalone: Default::default(),
+      inline_constants: Default::default(),
+      minify: Default::default(),
+      recover_parse_errors: Default::default(),
+    }
+  }
+}
+
+fn default_ascii_only() -> bool {
+  true
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[non_exhaustive]
 pub struct TransformResult {
   #[serde(with = "serde_bytes")]
@@ -118,6 +186,9 @@ pub struct TransformResult {
   pub used_env: HashSet<swc_core::ecma::atoms::JsWord>,
   pub has_node_replacements: bool,
   pub is_constant_module: bool,
+  /// The number of diagnostics at the start of `diagnostics` that are parse errors recovered from
+  /// with `Config::recover_parse_errors`, rather than errors from later transforms.
+  pub recovered_parse_errors: usize,
 }
 
 fn targets_to_versions(targets: &Option<HashMap<String, String>>) -> Option<Versions> {
@@ -150,6 +221,57 @@ fn targets_to_versions(targets: &Option<HashMap<String, String>>) -> Option<Vers
   None
 }
 
+/// Returns the newest ECMAScript version whose syntax is supported by all of the given versions.
+fn versions_to_es_version(versions: Versions) -> EsVersion {
+  let editions = [
+    (
+      EsVersion::Es2015,
+      &[
+        Feature::ArrowFunctions,
+        Feature::BlockScoping,
+        Feature::Classes,
+        Feature::TemplateLiterals,
+      ][..],
+    ),
+    (EsVersion::Es2016, &[Feature::ExponentiationOperator][..]),
+    (EsVersion::Es2017, &[Feature::AsyncToGenerator][..]),
+    (EsVersion::Es2018, &[Feature::ObjectRestSpread][..]),
+    (EsVersion::Es2019, &[Feature::OptionalCatchBinding][..]),
+    (
+      EsVersion::Es2020,
+      &[Feature::NullishCoalescing, Feature::OptionalChaining][..],
+    ),
+    (
+      EsVersion::Es2021,
+      &[
+        Feature::LogicalAssignmentOperators,
+        Feature::NumericSeparator,
+      ][..],
+    ),
+    (
+      EsVersion::Es2022,
+      &[
+        Feature::ClassProperties,
+        Feature::PrivateMethods,
+        Feature::ClassStaticBlock,
+      ][..],
+    ),
+  ];
+
+  let mut es_version = EsVersion::Es5;
+  for (edition, features) in editions {
+    if features
+      .iter()
+      .any(|feature| feature.should_enable(versions, true, false))
+    {
+      break;
+    }
+    es_version = edition;
+  }
+
+  es_version
+}
+
 pub fn transform(
   config: Config,
   call_macro: Option<MacroCallback>,
@@ -178,7 +300,7 @@ pub fn transform(
       result.diagnostics = Some(error_buffer_to_diagnostics(&error_buffer, &source_map));
       Ok(result)
     }
-    Ok((module, comments)) => {
+    Ok((module, comments, parse_errors)) => {
       let mut module = module;
       result.shebang = match &mut module {
         Program::Module(module) => module.shebang.take().map(|s| s.to_string()),
@@ -196,8 +318,21 @@ pub fn transform(
       };
 
       swc_core::common::GLOBALS.set(&Globals::new(), || {
+        // Errors recovered from while parsing fail the transform once dependencies are collected.
+        // They are reported before other diagnostics, so that callers can tell them apart.
+        let parse_diagnostics = {
+          let error_buffer = ErrorBuffer::default();
+          let handler = Handler::with_emitter(true, false, Box::new(error_buffer.clone()));
+          for err in parse_errors {
+            err.into_diagnostic(&handler).emit();
+          }
+          error_buffer_to_diagnostics(&error_buffer, &source_map)
+        };
+        result.recovered_parse_errors = parse_diagnostics.len();
+
         let error_buffer = ErrorBuffer::default();
         let handler = Handler::with_emitter(true, false, Box::new(error_buffer.clone()));
+
         swc_core::common::errors::HANDLER.set(&handler, || {
           helpers::HELPERS.set(
             &helpers::Helpers::new(
@@ -311,7 +446,7 @@ pub fn transform(
                 assumptions.set_public_class_fields |= true;
               }
 
-              let mut diagnostics = vec![];
+              let mut diagnostics = parse_diagnostics;
               if let Some(call_macro) = call_macro {
                 let mut errors = Vec::new();
                 module = module.fold_with(&mut Macros::new(call_macro, &source_map, &mut errors));
@@ -467,6 +602,7 @@ pub fn transform(
               if let Some(bailouts) = &collect.bailouts {
                 diagnostics.extend(bailouts.iter().map(|bailout| bailout.to_diagnostic()));
               }
+              let has_eval = collect.has_eval;
 
               let module = if config.scope_hoist {
                 let res = hoist(module, config.module_id.as_str(), unresolved_mark, &collect);
@@ -492,6 +628,21 @@ pub fn transform(
                 module
               };
 
+              let should_minify = config.minify && !config.scope_hoist;
+              let module = if should_minify {
+                minify(
+                  module,
+                  &source_map,
+                  &comments,
+                  unresolved_mark,
+                  global_mark,
+                  has_eval,
+                  config.source_type != SourceType::Script,
+                )
+              } else {
+                module
+              };
+
               let module = module.fold_with(&mut chain!(
                 reserved_words(),
                 hygiene(),
@@ -501,15 +652,45 @@ pub fn transform(
               result.dependencies.extend(global_deps);
               result.dependencies.extend(fs_deps);
 
+              let input_source_map = match &config.input_source_map {
+                Some(map) if config.source_maps => {
+                  match sourcemap::SourceMap::from_slice(map.as_bytes()) {
+                    Ok(map) => Some(map),
+                    Err(err) => {
+                      diagnostics.push(Diagnostic {
+                        message: format!("Could not parse the input source map: {}", err),
+                        code_highlights: None,
+                        hints: None,
+                        show_environment: false,
+                        severity: DiagnosticSeverity::Warning,
+                        documentation_url: None,
+                      });
+                      None
+                    }
+                  }
+                }
+                _ => None,
+              };
+
               if !diagnostics.is_empty() {
                 result.diagnostics = Some(diagnostics);
               }
 
-              let (buf, src_map_buf) =
-                emit(source_map.clone(), comments, &module, config.source_maps)?;
+              let target = config
+                .output_target
+                .or_else(|| versions.map(versions_to_es_version))
+                .unwrap_or(EsVersion::Es5);
+              let (buf, src_map_buf) = emit(
+                source_map.clone(),
+                comments,
+                &module,
+                config.source_maps,
+                target,
+                config.ascii_only,
+                should_minify,
+              )?;
               if config.source_maps
-                && source_map
-                  .build_source_map_with_config(&src_map_buf, None, SourceMapConfig)
+                && build_source_map(&source_map, &src_map_buf, input_source_map.as_ref())
                   .to_writer(&mut map_buf)
                   .is_ok()
               {
@@ -533,7 +714,7 @@ fn parse(
   filename: &str,
   source_map: &Lrc<SourceMap>,
   config: &Config,
-) -> ParseResult<(Program, SingleThreadedComments)> {
+) -> ParseResult<(Program, SingleThreadedComments, Vec<Error>)> {
   // Attempt to convert the path to be relative to the project root.
   // If outside the project root, use an absolute path so that if the project root moves the path still works.
   let filename: PathBuf = if let Ok(relative) = Path::new(filename).strip_prefix(project_root) {
@@ -580,11 +761,11 @@ fn parse(
   };
   // Recoverable errors
   let errors = parser.take_errors();
-  if !errors.is_empty() {
+  if !errors.is_empty() && !config.recover_parse_errors {
     return Err(errors);
   }
 
-  Ok((module, comments))
+  Ok((module, comments, errors))
 }
 
 fn emit(
@@ -592,6 +773,9 @@ fn emit(
   comments: SingleThreadedComments,
   module: &Module,
   source_maps: bool,
+  target: EsVersion,
+  ascii_only: bool,
+  minify: bool,
 ) -> Result<(Vec<u8>, SourceMapBuffer), std::io::Error> {
   let mut src_map_buf = vec![];
   let mut buf = vec![];
@@ -607,9 +791,9 @@ fn emit(
       },
     ));
     let config = swc_core::ecma::codegen::Config::default()
-      .with_target(swc_core::ecma::ast::EsVersion::Es5)
-      // Make sure the output works regardless of whether it's loaded with the correct (utf8) encoding
-      .with_ascii_only(true);
+      .with_target(target)
+      .with_ascii_only(ascii_only)
+      .with_minify(minify);
     let mut emitter = swc_core::ecma::codegen::Emitter {
       cfg: config,
       comments: Some(&comments),
@@ -623,6 +807,31 @@ fn emit(
   Ok((buf, src_map_buf))
 }
 
+/// Builds the source map for the emitted code. If there is an input source map, the mappings are
+/// composed with it, and the contents of the original sources are carried over.
+fn build_source_map(
+  source_map: &SourceMap,
+  mappings: &[(swc_core::common::BytePos, swc_core::common::LineCol)],
+  input_source_map: Option<&sourcemap::SourceMap>,
+) -> sourcemap::SourceMap {
+  let mut map =
+    source_map.build_source_map_with_config(mappings, input_source_map, SourceMapConfig);
+  if let Some(input_source_map) = input_source_map {
+    for idx in 0..map.get_source_count() {
+      let contents = map.get_source(idx).and_then(|source| {
+        (0..input_source_map.get_source_count())
+          .find(|i| input_source_map.get_source(*i) == Some(source))
+          .and_then(|i| input_source_map.get_source_contents(i))
+      });
+      if contents.is_some() {
+        map.set_source_contents(idx, contents);
+      }
+    }
+  }
+
+  map
+}
+
 // Exclude macro expansions from source maps.
 struct SourceMapConfig;
 impl SourceMapGenConfig for SourceMapConfig {
@@ -679,3 +888,142 @@ fn macro_error_to_diagnostic(error: MacroError, source_map: &SourceMap) -> Diagn
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::make_test_config;
+
+  fn make_config(recover_parse_errors: bool) -> Config {
+    Config {
+      recover_parse_errors,
+      ..make_test_config("import a from 'a';\nclass A { constructor() {} constructor() {} }")
+    }
+  }
+
+  #[test]
+  fn test_recover_parse_errors() {
+    let result = transform(make_config(false), None).unwrap();
+    assert!(result.dependencies.is_empty());
+    assert_eq!(result.diagnostics.unwrap().len(), 1);
+
+    let result = transform(make_config(true), None).unwrap();
+    assert_eq!(result.dependencies.len(), 1);
+    assert_eq!(&*result.dependencies[0].specifier, "a");
+    assert!(result.code.is_empty());
+    assert_eq!(result.recovered_parse_errors, 1);
+    let diagnostics = result.diagnostics.unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+
+    // Errors from later transforms are reported after the parse errors.
+    let code = "import a from 'a';\n\
+                class A { constructor() {} constructor() {} }\n\
+                new Worker('worker.js');";
+    let config = Config {
+      recover_parse_errors: true,
+      ..make_test_config(code)
+    };
+    let result = transform(config, None).unwrap();
+    assert_eq!(result.recovered_parse_errors, 1);
+    let diagnostics = result.diagnostics.unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+      diagnostics[1].message,
+      "Constructing a Worker with a string literal is not supported."
+    );
+  }
+
+  fn make_targets(targets: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+    Some(
+      targets
+        .iter()
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+        .collect(),
+    )
+  }
+
+  fn transform_with_targets(code: &str, targets: &[(&str, &str)], ascii_only: bool) -> String {
+    let config = Config {
+      targets: make_targets(targets),
+      ascii_only,
+      ..make_test_config(code)
+    };
+    let result = transform(config, None).unwrap();
+    String::from_utf8(result.code).unwrap()
+  }
+
+  #[test]
+  fn test_versions_to_es_version() {
+    let es_version = |targets: &[(&str, &str)]| {
+      versions_to_es_version(targets_to_versions(&make_targets(targets)).unwrap())
+    };
+    assert_eq!(es_version(&[("ie", "11")]), EsVersion::Es5);
+    assert_eq!(es_version(&[("chrome", "70")]), EsVersion::Es2019);
+    assert_eq!(es_version(&[("chrome", "100")]), EsVersion::Es2022);
+    // The version is limited by the oldest target.
+    assert_eq!(
+      es_version(&[("chrome", "100"), ("ie", "11")]),
+      EsVersion::Es5
+    );
+  }
+
+  #[test]
+  fn test_output_target() {
+    let code = "module.exports = a?.b ** 2;";
+    let output = transform_with_targets(code, &[("chrome", "100")], true);
+    assert!(output.contains("?."));
+    assert!(output.contains("**"));
+
+    let output = transform_with_targets(code, &[("ie", "11")], true);
+    assert!(!output.contains("?."));
+    assert!(!output.contains("**"));
+  }
+
+  #[test]
+  fn test_ascii_only() {
+    assert!(Config::default().ascii_only);
+
+    let code = "module.exports = '\u{1F600}';";
+    // ES5 has no code point escapes, so characters outside the BMP are escaped as surrogate pairs.
+    let output = transform_with_targets(code, &[("ie", "11")], true).to_lowercase();
+    assert!(output.contains("\\ud83d\\ude00"));
+
+    let output = transform_with_targets(code, &[("chrome", "100")], true).to_lowercase();
+    assert!(output.contains("\\u{1f600}"));
+
+    let output = transform_with_targets(code, &[("chrome", "100")], false);
+    assert!(output.contains('\u{1F600}'));
+  }
+
+  #[test]
+  fn test_input_source_map() {
+    let original = "// Compiled from TypeScript\n\n\n\n  console.log('hello');\n";
+    // Maps the first column of the compiled code to line 5, column 3 of the original.
+    let input_source_map = serde_json::json!({
+      "version": 3,
+      "sources": ["src/index.ts"],
+      "sourcesContent": [original],
+      "names": [],
+      "mappings": "AAIE",
+    });
+    let config = Config {
+      source_maps: true,
+      input_source_map: Some(input_source_map.to_string()),
+      ..make_test_config("console.log('hello');")
+    };
+    let result = transform(config, None).unwrap();
+    assert!(result.diagnostics.is_none());
+
+    let code = String::from_utf8(result.code).unwrap();
+    let offset = code.find("console").unwrap();
+    let line = code[..offset].matches('\n').count() as u32;
+    let col = (offset - code[..offset].rfind('\n').map_or(0, |i| i + 1)) as u32;
+
+    let map = sourcemap::SourceMap::from_slice(result.map.unwrap().as_bytes()).unwrap();
+    let token = map.lookup_token(line, col).unwrap();
+    assert_eq!(token.get_source(), Some("src/index.ts"));
+    assert_eq!((token.get_src_line(), token.get_src_col()), (4, 2));
+    assert_eq!(map.get_source_contents(token.get_src_id()), Some(original));
+  }
+}
@@ -0,0 +1,149 @@
+use swc_core::{
+  common::{
+    comments::{Comment, SingleThreadedComments},
+    sync::Lrc,
+    Mark, SourceMap,
+  },
+  ecma::{
+    ast::{Module, Program},
+    minifier::{
+      optimize,
+      option::{terser::TerserCompressorOptions, ExtraOptions, MangleOptions, MinifyOptions},
+    },
+  },
+};
+
+/// Compresses and mangles a module with SWC's minifier. The spans of the original nodes are kept, so
+/// the source map generated for the output still points to the original code.
+///
+/// If the module calls `eval`, names are not mangled, since the evaluated code may refer to any
+/// binding in scope. Top level names are only mangled if `top_level` is set, i.e. the module will be
+/// wrapped in a function rather than declaring globals.
+pub fn minify(
+  module: Module,
+  source_map: &Lrc<SourceMap>,
+  comments: &SingleThreadedComments,
+  unresolved_mark: Mark,
+  global_mark: Mark,
+  has_eval: bool,
+  top_level: bool,
+) -> Module {
+  let options = MinifyOptions {
+    compress: Some(TerserCompressorOptions::default().into_config(source_map.clone())),
+    mangle: if has_eval {
+      None
+    } else {
+      Some(MangleOptions {
+        top_level: Some(top_level),
+        ..Default::default()
+      })
+    },
+    ..Default::default()
+  };
+
+  let module = optimize(
+    Program::Module(module),
+    source_map.clone(),
+    Some(comments),
+    None,
+    &options,
+    &ExtraOptions {
+      unresolved_mark,
+      top_level_mark: global_mark,
+      mangle_name_cache: None,
+    },
+  )
+  .expect_module();
+
+  retain_preserved_comments(comments);
+  module
+}
+
+/// Removes all comments except for license comments, e.g. `/*! ... */` or ones with `@license`.
+fn retain_preserved_comments(comments: &SingleThreadedComments) {
+  let (mut leading, mut trailing) = comments.borrow_all_mut();
+  for map in [&mut *leading, &mut *trailing] {
+    map.retain(|_, comments| {
+      comments.retain(is_preserved_comment);
+      !comments.is_empty()
+    });
+  }
+}
+
+fn is_preserved_comment(comment: &Comment) -> bool {
+  comment.text.starts_with('!')
+    || comment.text.contains("@license")
+    || comment.text.contains("@preserve")
+}
+
+#[cfg(test)]
+mod tests {
+  use swc_core::common::sourcemap;
+
+  use crate::{test_utils::make_test_config, transform, Config};
+
+  fn minify(code: &str) -> String {
+    let config = Config {
+      minify: true,
+      ..make_test_config(code)
+    };
+    let result = transform(config, None).unwrap();
+    String::from_utf8(result.code).unwrap()
+  }
+
+  #[test]
+  fn test_minify() {
+    let output = minify(
+      r#"
+    /*! license */
+    // comment
+    module.exports = function add(first, second) {
+      return first + second;
+    };
+    "#,
+    );
+    assert!(output.contains("/*! license */"));
+    assert!(!output.contains("comment"));
+    assert!(!output.contains("first"));
+    assert!(output.contains("module.exports"));
+  }
+
+  #[test]
+  fn test_minify_eval() {
+    let output = minify(
+      r#"
+    module.exports = function add(first, second) {
+      return eval('first + second');
+    };
+    "#,
+    );
+    assert!(output.contains("first,second"));
+  }
+
+  #[test]
+  fn test_minify_source_map() {
+    let code = "module.exports = function add(first, second) {\n  return first + second;\n};\n";
+    let config = Config {
+      minify: true,
+      source_maps: true,
+      ..make_test_config(code)
+    };
+    let result = transform(config, None).unwrap();
+    let output = String::from_utf8(result.code).unwrap();
+
+    // The minified code is on a single line, and the first parameter is mangled.
+    let start = output.find("function").unwrap();
+    let start = start + output[start..].find('(').unwrap() + 1;
+    let end = start + output[start..].find(',').unwrap();
+    assert_ne!(&output[start..end], "first");
+
+    let map = sourcemap::SourceMap::from_slice(result.map.unwrap().as_bytes()).unwrap();
+    let token = map.lookup_token(0, start as u32).unwrap();
+    assert_eq!(token.get_dst_col(), start as u32);
+    assert_eq!(token.get_source(), Some("index.js"));
+    assert_eq!(
+      (token.get_src_line(), token.get_src_col()),
+      (0, code.find("first").unwrap() as u32)
+    );
+  }
+}
@@ -11,6 +11,8 @@ use swc_core::{
   },
 };
 
+use crate::Config;
+
 pub(crate) struct RunTestContext {
   /// Source-map in use
   pub source_map: Lrc<SourceMap>,
@@ -26,6 +28,17 @@ pub(crate) struct RunVisitResult<V> {
   pub visitor: V,
 }
 
+/// Config to transform `code` in a browser script named "index.js", for tests of `transform`.
+pub(crate) fn make_test_config(code: &str) -> Config {
+  Config {
+    filename: "index.js".into(),
+    code: code.as_bytes().to_vec(),
+    project_root: "/".into(),
+    is_browser: true,
+    ..Default::default()
+  }
+}
+
 /// Helper to test SWC visitors.
 ///
 /// * Parse `code` with SWC
@@ -109,6 +109,9 @@ const CONFIG_SCHEMA: SchemaEntity = {
     unstable_inlineConstants: {
       type: 'boolean',
     },
+    unstable_minify: {
+      type: 'boolean',
+    },
   },
   additionalProperties: false,
 };
@@ -285,6 +288,7 @@ export default (new Transformer({
     let inlineEnvironment = config.isSource;
     let inlineFS = !ignoreFS;
     let inlineConstants = false;
+    let minify = false;
     if (conf && conf.contents) {
       validateSchema.diagnostic(
         CONFIG_SCHEMA,
@@ -304,6 +308,7 @@ export default (new Transformer({
       inlineFS = conf.contents?.inlineFS ?? inlineFS;
       inlineConstants =
         conf.contents?.unstable_inlineConstants ?? inlineConstants;
+      minify = conf.contents?.unstable_minify ?? minify;
     }
 
     return {
@@ -315,6 +320,7 @@ export default (new Transformer({
       inlineEnvironment,
       inlineFS,
       inlineConstants,
+      minify,
       reactRefresh,
       decorators,
       useDefineForClassFields,
@@ -406,6 +412,14 @@ export default (new Transformer({
       }
     }
 
+    let inputSourceMap =
+      asset.env.sourceMap && originalMap
+        ? await originalMap.stringify({})
+        : null;
+
+    // While a file is being edited with HMR, keep the dependencies that can be collected from it
+    // even if it has errors, so that the asset graph stays stable.
+    let recoverParseErrors = Boolean(options.hmrOptions);
     let macroAssets = [];
     let {
       dependencies,
@@ -419,6 +433,7 @@ export default (new Transformer({
       used_env,
       has_node_replacements,
       is_constant_module,
+      recovered_parse_errors,
     } = await (transformAsync || transform)({
       filename: asset.filePath,
       code,
@@ -448,7 +463,10 @@ export default (new Transformer({
       decorators: Boolean(config?.decorators),
       use_define_for_class_fields: Boolean(config?.useDefineForClassFields),
       targets,
+      // Make sure the output works regardless of whether it's loaded with the correct (utf8) encoding
+      ascii_only: true,
       source_maps: !!asset.env.sourceMap,
+      input_source_map: inputSourceMap,
       scope_hoist:
         asset.env.shouldScopeHoist && asset.env.sourceType !== 'script',
       source_type: asset.env.sourceType === 'script' ? 'Script' : 'Module',
@@ -459,6 +477,8 @@ export default (new Transformer({
       is_swc_helpers: /@swc[/\\]helpers/.test(asset.filePath),
       standalone: asset.query.has('standalone'),
       inline_constants: config.inlineConstants,
+      minify: config.minify && asset.env.shouldOptimize,
+      recover_parse_errors: recoverParseErrors,
       callMacro: asset.isSource
         ? async (err, src, exportName, args, loc) => {
             let mod;
@@ -666,9 +686,27 @@ export default (new Transformer({
       };
 
       if (errors.length > 0) {
-        throw new ThrowableDiagnostic({
-          diagnostic: errors.map(convertDiagnostic),
-        });
+        // If nothing could be recovered, e.g. the file could not be parsed at all, fail the asset so
+        // that the dependencies from the previous build are kept instead. Other errors, e.g. from
+        // collecting dependencies or macros, always fail the asset.
+        if (
+          !recoverParseErrors ||
+          dependencies.length === 0 ||
+          errors.length > recovered_parse_errors
+        ) {
+          throw new ThrowableDiagnostic({
+            diagnostic: errors.map(convertDiagnostic),
+          });
+        }
+
+        // Otherwise, keep the recovered dependencies, report the errors, and throw them when the
+        // asset runs, since no code was generated for it.
+        logger.error(errors.map(convertDiagnostic));
+        compiledCode = Buffer.from(
+          `throw new SyntaxError(${JSON.stringify(
+            errors.map(error => error.message).join('\n'),
+          )});`,
+        );
       }
 
       logger.warn(warnings.map(convertDiagnostic));
@@ -1059,10 +1097,8 @@ export default (new Transformer({
 
     if (map) {
       let sourceMap = new SourceMap(options.projectRoot);
+      // The map is already composed with the original map, if any, by the transformer.
       sourceMap.addVLQMap(JSON.parse(map));
-      if (originalMap) {
-        sourceMap.extends(originalMap);
-      }
       asset.setMap(sourceMap);
     }
 
@@ -51,6 +51,9 @@ type Options = {|
   mainFields?: Array<string>,
   extensions?: Array<string>,
   packageExports?: boolean,
+  caseSensitive?: boolean,
+  workspaces?: boolean,
+  preserveSymlinks?: boolean,
 |};
 
 type ResolveOptions = {|
@@ -67,10 +70,23 @@ type ResolveOptions = {|
 export default class NodeResolver {
   resolversByEnv: Map<string, any>;
   options: Options;
+  nativePnp: boolean;
 
   constructor(options: Options) {
     this.options = options;
     this.resolversByEnv = new Map();
+
+    // Yarn's .pnp.cjs manifests can be read by the native resolver directly.
+    // Older .pnp.js APIs are resolved via a JS callback instead.
+    this.nativePnp =
+      process.versions.pnp != null &&
+      options.fs instanceof NodeFS &&
+      !init &&
+      // $FlowFixMe[prop-missing]
+      _Module
+        .findPnpApi?.(options.projectRoot)
+        ?.resolveToUnqualified('pnpapi', null)
+        ?.endsWith('.cjs') === true;
   }
 
   async resolve(options: ResolveOptions): Promise<?ResolveResult> {
@@ -85,7 +101,7 @@ export default class NodeResolver {
       resolver = new Resolver(this.options.projectRoot, {
         fs:
           this.options.fs instanceof NodeFS &&
-          process.versions.pnp == null &&
+          (process.versions.pnp == null || this.nativePnp) &&
           // For Wasm builds
           !init
             ? undefined
@@ -106,8 +122,12 @@ export default class NodeResolver {
           this.options.mode,
         ),
         packageExports: this.options.packageExports ?? false,
+        pnp: this.nativePnp,
+        caseSensitive: this.options.caseSensitive ?? false,
+        workspaces: this.options.workspaces ?? false,
+        preserveSymlinks: this.options.preserveSymlinks ?? false,
         moduleDirResolver:
-          process.versions.pnp != null
+          process.versions.pnp != null && !this.nativePnp
             ? (module, from) => {
                 // $FlowFixMe[prop-missing]
                 let pnp = _Module.findPnpApi(path.dirname(from));
@@ -134,11 +154,9 @@ export default class NodeResolver {
       }
     }
 
-    // Async resolver is only supported in non-WASM environments, and does not support JS callbacks (e.g. FS, PnP).
+    // Async resolver is only supported in non-WASM environments, and does not support the JS PnP callback.
     let canResolveAsync =
-      !init &&
-      this.options.fs instanceof NodeFS &&
-      process.versions.pnp == null;
+      !init && (process.versions.pnp == null || this.nativePnp);
 
     let res = canResolveAsync
       ? // $FlowFixMe[incompatible-call] - parent is not null here.
@@ -150,6 +168,7 @@ export default class NodeResolver {
     // TODO: only when we actually resolve a node_modules package?
     if (
       process.versions.pnp != null &&
+      !this.nativePnp &&
       options.parent &&
       res.invalidateOnFileChange
     ) {
@@ -168,6 +187,7 @@ export default class NodeResolver {
           : undefined,
         invalidateOnFileCreate: res.invalidateOnFileCreate,
         invalidateOnFileChange: res.invalidateOnFileChange,
+        invalidateOnStartup: res.invalidateOnStartup,
       };
     }
 
@@ -177,6 +197,7 @@ export default class NodeResolver {
           filePath: res.resolution.value,
           invalidateOnFileCreate: res.invalidateOnFileCreate,
           invalidateOnFileChange: res.invalidateOnFileChange,
+          invalidateOnStartup: res.invalidateOnStartup,
           sideEffects: res.sideEffects,
           query: res.query != null ? new URLSearchParams(res.query) : undefined,
         };
@@ -198,6 +219,7 @@ export default class NodeResolver {
               diagnostics: [diagnostic],
               invalidateOnFileCreate: res.invalidateOnFileCreate,
               invalidateOnFileChange: res.invalidateOnFileChange,
+              invalidateOnStartup: res.invalidateOnStartup,
             };
           }
         }
@@ -206,6 +228,7 @@ export default class NodeResolver {
           isExcluded: true,
           invalidateOnFileCreate: res.invalidateOnFileCreate,
           invalidateOnFileChange: res.invalidateOnFileChange,
+          invalidateOnStartup: res.invalidateOnStartup,
         };
       }
       case 'Empty':
@@ -213,6 +236,7 @@ export default class NodeResolver {
           filePath: empty,
           invalidateOnFileCreate: res.invalidateOnFileCreate,
           invalidateOnFileChange: res.invalidateOnFileChange,
+          invalidateOnStartup: res.invalidateOnStartup,
         };
       case 'Global': {
         let global = res.resolution.value;
@@ -221,6 +245,7 @@ export default class NodeResolver {
           code: `module.exports=${global};`,
           invalidateOnFileCreate: res.invalidateOnFileCreate,
           invalidateOnFileChange: res.invalidateOnFileChange,
+          invalidateOnStartup: res.invalidateOnStartup,
         };
       }
       default:
@@ -387,15 +412,24 @@ export default class NodeResolver {
           relative = './' + relative;
         }
 
-        let potentialFiles = await findAlternativeFiles(
-          this.options.fs,
-          relative,
-          dir,
-          this.options.projectRoot,
-          true,
-          options.specifierType !== 'url',
-          // extensions.length === 0,
-        );
+        // Prefer suggestions from the native resolver. Empty lists are not
+        // serialized, so this also searches in JS when the native resolver found
+        // nothing, e.g. because the file system does not support listing
+        // directories. Native suggestions are in the same form as
+        // error.relative, so only make bare paths relative.
+        let potentialFiles = error.suggestions
+          ? error.suggestions.map(s =>
+              s.startsWith('.') || path.isAbsolute(s) ? s : './' + s,
+            )
+          : await findAlternativeFiles(
+              this.options.fs,
+              relative,
+              dir,
+              this.options.projectRoot,
+              true,
+              options.specifierType !== 'url',
+              // extensions.length === 0,
+            );
 
         return {
           message: md`Cannot load file '${relative}' in '${relativePath(
@@ -408,13 +442,15 @@ export default class NodeResolver {
         };
       }
       case 'ModuleNotFound': {
-        let alternativeModules = await findAlternativeNodeModules(
-          this.options.fs,
-          error.module,
-          options.parent
-            ? path.dirname(options.parent)
-            : this.options.projectRoot,
-        );
+        let alternativeModules =
+          error.suggestions ??
+          (await findAlternativeNodeModules(
+            this.options.fs,
+            error.module,
+            options.parent
+              ? path.dirname(options.parent)
+              : this.options.projectRoot,
+          ));
 
         return {
           message: md`Cannot find module '${error.module}'`,
@@ -466,15 +502,16 @@ export default class NodeResolver {
           'utf8',
         );
         let pkg = JSON.parse(pkgContent);
-        let potentialFiles = [];
-        if (!pkg.exports) {
-          potentialFiles = await findAlternativeFiles(
+        let alternatives = error.suggestions ?? [];
+        if (!error.suggestions && !pkg.exports) {
+          let potentialFiles = await findAlternativeFiles(
             this.options.fs,
             relative,
             dir,
             this.options.projectRoot,
             false,
           );
+          alternatives = potentialFiles.map(r => `${error.module}/${r}`);
         }
 
         if (!relative.startsWith('.')) {
@@ -483,11 +520,25 @@ export default class NodeResolver {
 
         return {
           message: md`Cannot load file '${relative}' from module '${error.module}'`,
-          hints: potentialFiles.map(r => {
-            return `Did you mean '__${error.module}/${r}__'?`;
+          hints: alternatives.map(r => {
+            return `Did you mean '__${r}__'?`;
           }),
         };
       }
+      case 'IncorrectCase': {
+        return {
+          message: md`File name '${relativePath(
+            this.options.projectRoot,
+            error.path,
+          )}' differs from '${relativePath(
+            this.options.projectRoot,
+            error.actual_path,
+          )}' only in casing`,
+          hints: [
+            'Update the specifier to match the casing on disk, otherwise it will fail to resolve on case-sensitive file systems.',
+          ],
+        };
+      }
       case 'JsonError': {
         let pkgContent = await this.options.fs.readFile(error.path, 'utf8');
         return {
@@ -622,6 +673,24 @@ export default class NodeResolver {
           )}'`,
         };
       }
+      case 'ImportMapError': {
+        let importMap = relativePath(this.options.projectRoot, error.import_map);
+        switch (error.error) {
+          case 'NullEntry':
+            return {
+              message: md`Module '${options.filename}' is blocked by the '${error.key}' entry in the import map '${importMap}'`,
+            };
+          case 'InvalidTarget':
+            return {
+              message: md`Invalid target for the '${error.key}' entry in the import map '${importMap}'`,
+            };
+          case 'BacktrackingSpecifier':
+            return {
+              message: md`Module '${options.filename}' is outside the target of the '${error.key}' entry in the import map '${importMap}'`,
+            };
+        }
+        break;
+      }
       case 'TsConfigExtendsNotFound': {
         let tsconfigContent = await this.options.fs.readFile(
           error.tsconfig,
@@ -0,0 +1 @@
+require("browser-files");
@@ -0,0 +1 @@
+export default 'src/legacy/index.js';
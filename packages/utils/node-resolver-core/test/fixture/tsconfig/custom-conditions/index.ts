@@ -0,0 +1 @@
+import 'tsconfig-conditions';
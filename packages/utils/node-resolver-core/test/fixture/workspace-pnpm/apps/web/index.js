@@ -0,0 +1 @@
+import "@pnpm/core";
@@ -0,0 +1 @@
+import "@monorepo/ui";
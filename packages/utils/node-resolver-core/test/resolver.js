@@ -8,6 +8,7 @@ import {loadConfig as configCache} from '@parcel/utils';
 import {createEnvironment} from '@parcel/core/src/Environment';
 import Environment from '@parcel/core/src/public/Environment';
 import {DEFAULT_OPTIONS} from '@parcel/core/test/test-utils';
+import {init, Resolver} from '@parcel/rust';
 
 const rootDir = path.join(__dirname, 'fixture');
 
@@ -2949,4 +2950,210 @@ describe('resolver', function () {
       );
     });
   });
+
+  describe('native resolver', function () {
+    function createResolver() {
+      return new Resolver(rootDir, {
+        fs: {
+          canonicalize: path => overlayFS.realpathSync(path),
+          read: path => overlayFS.readFileSync(path),
+          isFile: path => overlayFS.statSync(path).isFile(),
+          isDir: path => overlayFS.statSync(path).isDirectory(),
+        },
+        mode: 1,
+        packageExports: true,
+      });
+    }
+
+    it('should invalidate the cache while async resolutions are pending', async function () {
+      // Async resolution is not supported in Wasm builds.
+      if (init) {
+        return;
+      }
+
+      let resolver = createResolver();
+      let pkg = path.join(
+        rootDir,
+        'node_modules',
+        'package-main',
+        'package.json',
+      );
+      let pending = [];
+      for (let i = 0; i < 20; i++) {
+        pending.push(
+          resolver.resolveAsync({
+            filename: 'package-main',
+            specifierType: 'esm',
+            parent: path.join(rootDir, 'foo.js'),
+          }),
+        );
+        resolver.invalidateEvents([{path: pkg, type: 'update'}]);
+      }
+
+      for (let res of await Promise.all(pending)) {
+        assert.deepEqual(res.resolution, {
+          type: 'Path',
+          value: path.join(
+            rootDir,
+            'node_modules',
+            'package-main',
+            'main.js',
+          ),
+        });
+      }
+
+      await overlayFS.writeFile(
+        pkg,
+        JSON.stringify({name: 'package-main', main: 'bar.js'}),
+      );
+      await overlayFS.writeFile(
+        path.join(rootDir, 'node_modules', 'package-main', 'bar.js'),
+        '',
+      );
+      let res = resolver.resolveAsync({
+        filename: 'package-main',
+        specifierType: 'esm',
+        parent: path.join(rootDir, 'foo.js'),
+      });
+      resolver.invalidateEvents([{path: pkg, type: 'update'}]);
+      await res;
+
+      res = await resolver.resolveAsync({
+        filename: 'package-main',
+        specifierType: 'esm',
+        parent: path.join(rootDir, 'foo.js'),
+      });
+      assert.equal(
+        res.resolution.value,
+        path.join(rootDir, 'node_modules', 'package-main', 'bar.js'),
+      );
+    });
+
+    it('should resolve async with a JS file system', async function () {
+      if (init) {
+        return;
+      }
+
+      let resolver = createResolver();
+      let res = await resolver.resolveAsync({
+        filename: './bar',
+        specifierType: 'esm',
+        parent: path.join(rootDir, 'foo.js'),
+      });
+      assert.deepEqual(res.resolution, {
+        type: 'Path',
+        value: path.join(rootDir, 'bar.js'),
+      });
+      assert(
+        res.invalidateOnFileChange.includes(path.join(rootDir, 'package.json')),
+      );
+
+      // Files only in the overlay are read through the JS file system.
+      await overlayFS.writeFile(path.join(rootDir, 'overlay-only.js'), '');
+      res = await resolver.resolveAsync({
+        filename: './overlay-only',
+        specifierType: 'esm',
+        parent: path.join(rootDir, 'foo.js'),
+      });
+      assert.deepEqual(res.resolution, {
+        type: 'Path',
+        value: path.join(rootDir, 'overlay-only.js'),
+      });
+    });
+
+    it('should resolve many requests in parallel', async function () {
+      if (init) {
+        return;
+      }
+
+      let resolver = createResolver();
+      let parent = path.join(rootDir, 'foo.js');
+      let res = await resolver.resolveMany([
+        {filename: './bar', specifierType: 'esm', parent, trace: true},
+        {
+          filename: 'side-effects-false',
+          specifierType: 'esm',
+          parent,
+          explainSideEffects: true,
+        },
+        {filename: './missing', specifierType: 'esm', parent},
+      ]);
+
+      assert.equal(res.results.length, 3);
+      assert.deepEqual(res.results[0].resolution, {
+        type: 'Path',
+        value: path.join(rootDir, 'bar.js'),
+      });
+      assert(res.results[0].trace.length > 0);
+      assert.equal(res.results[0].sideEffectsExplanation, undefined);
+
+      assert.deepEqual(res.results[1].resolution, {
+        type: 'Path',
+        value: path.join(
+          rootDir,
+          'node_modules/side-effects-false/src/index.js',
+        ),
+      });
+      assert.equal(res.results[1].sideEffects, false);
+      assert.equal(res.results[1].sideEffectsExplanation.sideEffects, false);
+      assert.equal(res.results[1].trace, undefined);
+
+      assert.equal(res.results[2].resolution, undefined);
+      assert.equal(res.results[2].error.type, 'FileNotFound');
+
+      // Invalidations are merged across all requests.
+      assert(
+        res.invalidateOnFileChange.includes(
+          path.join(rootDir, 'node_modules/side-effects-false/package.json'),
+        ),
+      );
+      assert(
+        res.invalidateOnFileCreate.some(
+          i => i.filePath === path.join(rootDir, 'missing.js'),
+        ),
+      );
+    });
+
+    it('should invalidate on startup when resolving from global folders', function () {
+      let nodePath = process.env.NODE_PATH;
+      process.env.NODE_PATH = path.join(rootDir, 'node-path');
+      let resolver;
+      try {
+        resolver = new Resolver(rootDir, {
+          fs: {
+            canonicalize: path => overlayFS.realpathSync(path),
+            read: path => overlayFS.readFileSync(path),
+            isFile: path => overlayFS.statSync(path).isFile(),
+            isDir: path => overlayFS.statSync(path).isDirectory(),
+          },
+          mode: 2,
+        });
+      } finally {
+        if (nodePath == null) {
+          delete process.env.NODE_PATH;
+        } else {
+          process.env.NODE_PATH = nodePath;
+        }
+      }
+
+      let parent = path.join(rootDir, 'foo.js');
+      let res = resolver.resolve({
+        filename: 'global-pkg',
+        specifierType: 'commonjs',
+        parent,
+      });
+      assert.deepEqual(res.resolution, {
+        type: 'Path',
+        value: path.join(rootDir, 'node-path', 'global-pkg', 'main.js'),
+      });
+      assert.equal(res.invalidateOnStartup, true);
+
+      res = resolver.resolve({
+        filename: './bar',
+        specifierType: 'commonjs',
+        parent,
+      });
+      assert.equal(res.invalidateOnStartup, false);
+    });
+  });
 });
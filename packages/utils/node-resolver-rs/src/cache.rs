@@ -1,5 +1,6 @@
 use std::{
   borrow::Cow,
+  collections::{HashMap, HashSet},
   fmt,
   ops::Deref,
   path::{Path, PathBuf},
@@ -12,9 +13,13 @@ use parking_lot::Mutex;
 use typed_arena::Arena;
 
 use crate::{
-  fs::{FileSystem, FileSystemRealPathCache},
+  fs::{FileMetadata, FileSystem, FileSystemRealPathCache},
+  import_map::ImportMap,
   package_json::{PackageJson, SourceField},
+  pnp::PnpManifest,
+  snapshot::{SnapshotReader, SnapshotWriter},
   tsconfig::{TsConfig, TsConfigWrapper},
+  workspace::Workspace,
   ResolverError,
 };
 
@@ -28,9 +33,45 @@ pub struct Cache {
   /// to insert into. Since each value is in a Box, it won't move and therefore references are stable.
   packages: FrozenMap<PathBuf, Box<Result<PackageJson<'static>, ResolverError>>>,
   tsconfigs: FrozenMap<PathBuf, Box<Result<TsConfigWrapper<'static>, ResolverError>>>,
+  pnp_manifests: FrozenMap<PathBuf, Box<Result<PnpManifest, ResolverError>>>,
+  workspaces: FrozenMap<PathBuf, Box<Result<Workspace, ResolverError>>>,
+  import_maps: FrozenMap<PathBuf, Box<Result<ImportMap, ResolverError>>>,
   is_file_cache: DashMap<PathBuf, bool, xxhash_rust::xxh3::Xxh3Builder>,
   is_dir_cache: DashMap<PathBuf, bool, xxhash_rust::xxh3::Xxh3Builder>,
+  read_dir_cache: DashMap<PathBuf, Option<Arc<[String]>>, xxhash_rust::xxh3::Xxh3Builder>,
   realpath_cache: FileSystemRealPathCache,
+  /// The contents of the package.json and tsconfig.json files that were read, for snapshots.
+  /// These are only kept if `track_files` is set.
+  files: DashMap<PathBuf, CachedFile, xxhash_rust::xxh3::Xxh3Builder>,
+  /// The metadata of the directories containing the paths in the stat caches above, recorded
+  /// before the first of them was cached, or None if the directory did not exist. Snapshots
+  /// only include the entries of these directories. These are only kept if `track_files` is set.
+  dirs: DashMap<PathBuf, Option<FileMetadata>, xxhash_rust::xxh3::Xxh3Builder>,
+  track_files: bool,
+}
+
+#[derive(Clone)]
+struct CachedFile {
+  contents: Arc<str>,
+  metadata: Option<FileMetadata>,
+}
+
+impl CachedFile {
+  fn new(fs: &dyn FileSystem, path: &Path, contents: String) -> CachedFile {
+    CachedFile {
+      contents: Arc::from(contents),
+      metadata: fs.metadata(path).ok(),
+    }
+  }
+}
+
+/// The cached stat results for the entries of a directory, grouped for snapshots.
+#[derive(Default)]
+struct CachedDir {
+  is_file: Vec<(String, bool)>,
+  is_dir: Vec<(String, bool)>,
+  read_dir: Option<Option<Arc<[String]>>>,
+  realpath: Vec<(String, Option<PathBuf>)>,
 }
 
 impl fmt::Debug for Cache {
@@ -57,6 +98,34 @@ impl<'a> Deref for CacheCow<'a> {
   }
 }
 
+impl<'a> CacheCow<'a> {
+  /// Returns a mutable reference to the cache if it is owned.
+  pub fn as_mut(&mut self) -> Option<&mut Cache> {
+    match self {
+      CacheCow::Borrowed(_) => None,
+      CacheCow::Owned(c) => Some(c),
+    }
+  }
+}
+
+/// A file system change reported by a watcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileSystemEvent {
+  Create(PathBuf),
+  Update(PathBuf),
+  Delete(PathBuf),
+}
+
+impl FileSystemEvent {
+  pub fn path(&self) -> &Path {
+    match self {
+      FileSystemEvent::Create(path)
+      | FileSystemEvent::Update(path)
+      | FileSystemEvent::Delete(path) => path,
+    }
+  }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub struct JsonError {
   pub path: PathBuf,
@@ -66,7 +135,7 @@ pub struct JsonError {
 }
 
 impl JsonError {
-  fn new(path: PathBuf, err: serde_json::Error) -> JsonError {
+  pub(crate) fn new(path: PathBuf, err: serde_json::Error) -> JsonError {
     JsonError {
       path,
       line: err.line(),
@@ -83,9 +152,208 @@ impl Cache {
       arena: Mutex::new(Arena::new()),
       packages: FrozenMap::new(),
       tsconfigs: FrozenMap::new(),
+      pnp_manifests: FrozenMap::new(),
+      workspaces: FrozenMap::new(),
+      import_maps: FrozenMap::new(),
       is_file_cache: DashMap::default(),
       is_dir_cache: DashMap::default(),
+      read_dir_cache: DashMap::default(),
       realpath_cache: DashMap::default(),
+      files: DashMap::default(),
+      dirs: DashMap::default(),
+      track_files: false,
+    }
+  }
+
+  /// Creates a cache that keeps the contents of the files it reads, so that they can be written
+  /// to a snapshot with `write_snapshot`. This costs a copy and a metadata query per file read,
+  /// and a metadata query per directory whose entries are checked.
+  pub fn with_snapshots(fs: Arc<dyn FileSystem>) -> Self {
+    Self {
+      track_files: true,
+      ..Cache::new(fs)
+    }
+  }
+
+  /// Loads a snapshot written by `write_snapshot`, e.g. in a previous process, so that files
+  /// do not need to be read again. Files that changed since then, according to their modification
+  /// time and size, are read again, as are all files if the file system does not support metadata.
+  /// Parsed package.json and tsconfig.json files are not stored, but are parsed again when needed.
+  /// The returned cache keeps tracking files, like one created with `with_snapshots`.
+  ///
+  /// The results of `is_file`, `is_dir`, `read_dir` and `canonicalize` are stored per directory,
+  /// and are kept if the directory's modification time and size did not change, which is the case
+  /// unless entries were added, removed or renamed. Results for a symlink are not revalidated if
+  /// only its target changes.
+  pub fn from_snapshot(fs: Arc<dyn FileSystem>, data: &[u8]) -> std::io::Result<Self> {
+    let cache = Cache::with_snapshots(fs);
+    let mut reader = SnapshotReader::new(data)?;
+
+    for _ in 0..reader.len()? {
+      let path = reader.path()?;
+      let contents = reader.str()?;
+      let metadata = reader.option(|r| {
+        Ok(FileMetadata {
+          modified: r.u64()?,
+          len: r.u64()?,
+        })
+      })?;
+
+      match cache.fs.metadata(&path) {
+        Ok(current) if metadata == Some(current) => {
+          let file = CachedFile {
+            contents: Arc::from(contents),
+            metadata,
+          };
+          cache.files.insert(path, file);
+          continue;
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+        _ => {}
+      }
+
+      // Deleted files are skipped, and changed files are kept with their new contents.
+      if let Ok(contents) = cache.fs.read_to_string(&path) {
+        let file = CachedFile::new(&*cache.fs, &path, contents);
+        cache.files.insert(path, file);
+      }
+    }
+
+    for _ in 0..reader.len()? {
+      let dir = reader.path()?;
+      let metadata = reader.option(|r| {
+        Ok(FileMetadata {
+          modified: r.u64()?,
+          len: r.u64()?,
+        })
+      })?;
+      let is_file = read_entries(&mut reader, |r| r.bool())?;
+      let is_dir = read_entries(&mut reader, |r| r.bool())?;
+      let read_dir = reader.option(|r| {
+        r.option(|r| {
+          (0..r.len()?)
+            .map(|_| Ok(r.str()?.to_owned()))
+            .collect::<std::io::Result<Arc<[String]>>>()
+        })
+      })?;
+      let realpath = read_entries(&mut reader, |r| r.option(|r| r.path()))?;
+
+      let unchanged = match (metadata, cache.fs.metadata(&dir)) {
+        (Some(metadata), Ok(current)) => metadata == current,
+        (None, Err(err)) => err.kind() == std::io::ErrorKind::NotFound,
+        _ => false,
+      };
+      if !unchanged {
+        continue;
+      }
+
+      for (name, is_file) in is_file {
+        cache.is_file_cache.insert(dir.join(name), is_file);
+      }
+      for (name, is_dir) in is_dir {
+        cache.is_dir_cache.insert(dir.join(name), is_dir);
+      }
+      if let Some(entries) = read_dir {
+        cache.read_dir_cache.insert(dir.clone(), entries);
+      }
+      for (name, link) in realpath {
+        cache.realpath_cache.insert(dir.join(name), link);
+      }
+      cache.dirs.insert(dir, metadata);
+    }
+
+    if !reader.is_empty() {
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "unexpected data at the end of snapshot",
+      ));
+    }
+
+    Ok(cache)
+  }
+
+  /// Writes the contents of the files that were read to a binary snapshot that can be loaded
+  /// with `from_snapshot`. This is empty unless the cache was created with `with_snapshots`.
+  pub fn write_snapshot(&self) -> Vec<u8> {
+    let mut writer = SnapshotWriter::new();
+
+    writer.len(self.files.len());
+    for entry in self.files.iter() {
+      let file = entry.value();
+      writer.path(entry.key());
+      writer.str(&file.contents);
+      writer.option(file.metadata, |w, metadata| {
+        w.u64(metadata.modified);
+        w.u64(metadata.len);
+      });
+    }
+
+    // Group the stat results by the directory whose metadata validates them.
+    let mut dirs: HashMap<PathBuf, CachedDir> = HashMap::new();
+    for e in self.is_file_cache.iter() {
+      if let Some((dir, name)) = dir_entry(&mut dirs, e.key()) {
+        dir.is_file.push((name, *e.value()));
+      }
+    }
+    for e in self.is_dir_cache.iter() {
+      if let Some((dir, name)) = dir_entry(&mut dirs, e.key()) {
+        dir.is_dir.push((name, *e.value()));
+      }
+    }
+    for e in self.realpath_cache.iter() {
+      if let Some((dir, name)) = dir_entry(&mut dirs, e.key()) {
+        dir.realpath.push((name, e.value().clone()));
+      }
+    }
+    for e in self.read_dir_cache.iter() {
+      dirs.entry(e.key().clone()).or_default().read_dir = Some(e.value().clone());
+    }
+
+    // Directories whose metadata was not recorded cannot be validated, so they are skipped.
+    let dirs: Vec<_> = dirs
+      .into_iter()
+      .filter_map(|(path, dir)| Some((*self.dirs.get(&path)?, path, dir)))
+      .collect();
+    writer.len(dirs.len());
+    for (metadata, path, dir) in dirs {
+      writer.path(&path);
+      writer.option(metadata, |w, metadata| {
+        w.u64(metadata.modified);
+        w.u64(metadata.len);
+      });
+      write_entries(&mut writer, &dir.is_file, |w, v| w.bool(*v));
+      write_entries(&mut writer, &dir.is_dir, |w, v| w.bool(*v));
+      writer.option(dir.read_dir, |w, entries| {
+        w.option(entries, |w, entries| {
+          w.len(entries.len());
+          for entry in entries.iter() {
+            w.str(entry);
+          }
+        })
+      });
+      write_entries(&mut writer, &dir.realpath, |w, link| {
+        w.option(link.as_deref(), |w, link| w.path(link))
+      });
+    }
+
+    writer.finish()
+  }
+
+  /// Records the metadata of a directory before the results for its entries are first cached,
+  /// so that snapshots can check whether they changed.
+  fn track_dir(&self, dir: &Path) {
+    if !self.track_files || self.dirs.contains_key(dir) {
+      return;
+    }
+
+    match self.fs.metadata(dir) {
+      Ok(metadata) => {
+        self.dirs.insert(dir.to_owned(), Some(metadata));
+      }
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        self.dirs.insert(dir.to_owned(), None);
+      }
+      Err(_) => {}
     }
   }
 
@@ -94,6 +362,9 @@ impl Cache {
       return *is_file;
     }
 
+    if let Some(parent) = path.parent() {
+      self.track_dir(parent);
+    }
     let is_file = self.fs.is_file(path);
     self.is_file_cache.insert(path.to_path_buf(), is_file);
     is_file
@@ -104,12 +375,33 @@ impl Cache {
       return *is_file;
     }
 
+    if let Some(parent) = path.parent() {
+      self.track_dir(parent);
+    }
     let is_file = self.fs.is_dir(path);
     self.is_dir_cache.insert(path.to_path_buf(), is_file);
     is_file
   }
 
+  /// Returns the names of the entries in a directory, or None if it cannot be listed.
+  pub fn read_dir(&self, path: &Path) -> Option<Arc<[String]>> {
+    if let Some(entries) = self.read_dir_cache.get(path) {
+      return entries.clone();
+    }
+
+    self.track_dir(path);
+    let entries = self.fs.read_dir(path).ok().map(Arc::from);
+    self
+      .read_dir_cache
+      .insert(path.to_path_buf(), entries.clone());
+    entries
+  }
+
   pub fn canonicalize(&self, path: &Path) -> Result<PathBuf, ResolverError> {
+    // Components reached via symlinks are not tracked, so they are not included in snapshots.
+    for dir in path.ancestors().skip(1) {
+      self.track_dir(dir);
+    }
     Ok(self.fs.canonicalize(path, &self.realpath_cache)?)
   }
 
@@ -122,9 +414,10 @@ impl Cache {
       fs: &'fs dyn FileSystem,
       realpath_cache: &FileSystemRealPathCache,
       arena: &Mutex<Arena<Box<str>>>,
+      files: Option<&DashMap<PathBuf, CachedFile, xxhash_rust::xxh3::Xxh3Builder>>,
       path: PathBuf,
     ) -> Result<PackageJson<'static>, ResolverError> {
-      let contents: &str = read(fs, arena, &path)?;
+      let contents: &str = read(fs, arena, files, &path)?;
       let mut pkg =
         PackageJson::parse(path.clone(), contents).map_err(|e| JsonError::new(path, e))?;
 
@@ -133,6 +426,7 @@ impl Cache {
       // - and the realpath to the packages does not includes `node_modules`.
       // Since such package is likely a pre-compiled module
       // installed with package managers, rather than including a source code.
+      // Workspace packages are resolved from their own directories, so keep it for them.
       if !matches!(pkg.source, SourceField::None) {
         let realpath = fs.canonicalize(&pkg.path, realpath_cache)?;
         if realpath == pkg.path
@@ -140,7 +434,7 @@ impl Cache {
             .components()
             .any(|c| c.as_os_str() == "node_modules")
         {
-          pkg.source = SourceField::None;
+          pkg.unlinked_source = std::mem::take(&mut pkg.source);
         }
       }
 
@@ -154,6 +448,7 @@ impl Cache {
         &*self.fs,
         &self.realpath_cache,
         &self.arena,
+        self.track_files.then_some(&self.files),
         path,
       )),
     );
@@ -161,6 +456,151 @@ impl Cache {
     clone_result(pkg)
   }
 
+  /// Removes all cached information about a path, and any paths inside it if it is a directory.
+  pub fn invalidate(&mut self, path: &Path) {
+    self.invalidate_events(&[FileSystemEvent::Update(path.to_owned())]);
+  }
+
+  /// Removes cached information affected by a batch of file system events.
+  /// This requires a mutable reference so that no parsed package.json or tsconfig.json
+  /// files can be borrowed while they are removed. Note that the file contents remain
+  /// in the arena until the cache is dropped.
+  pub fn invalidate_events(&mut self, events: &[FileSystemEvent]) {
+    let mut paths = HashSet::new();
+    let mut parents = HashSet::new();
+    for event in events {
+      let path = event.path();
+      paths.insert(path);
+
+      // Creating or deleting a file changes the entries of its parent directory.
+      if !matches!(event, FileSystemEvent::Update(..)) {
+        if let Some(parent) = path.parent() {
+          parents.insert(parent);
+        }
+      }
+
+      // A created file may be inside directories that did not exist before.
+      if matches!(event, FileSystemEvent::Create(..)) {
+        for dir in path.ancestors().skip(1) {
+          if matches!(self.is_dir_cache.get(dir), Some(is_dir) if !*is_dir) {
+            self.is_dir_cache.remove(dir);
+            if let Some(parent) = dir.parent() {
+              parents.insert(parent);
+            }
+          }
+        }
+      }
+    }
+
+    if paths.is_empty() {
+      return;
+    }
+
+    for path in &paths {
+      self.fs.invalidate(path);
+    }
+
+    // Entries are affected if the path or one of its parent directories changed.
+    let is_affected = |p: &Path| p.ancestors().any(|a| paths.contains(a));
+
+    self.is_file_cache.retain(|p, _| !is_affected(p));
+    self.is_dir_cache.retain(|p, _| !is_affected(p));
+    self
+      .read_dir_cache
+      .retain(|p, _| !is_affected(p) && !parents.contains(p.as_path()));
+    self.realpath_cache.retain(|p, _| !is_affected(p));
+    self.files.retain(|p, _| !is_affected(p));
+    self
+      .dirs
+      .retain(|p, _| !is_affected(p) && !parents.contains(p.as_path()));
+    self.packages.as_mut().retain(|p, _| !is_affected(p));
+    self.import_maps.as_mut().retain(|p, _| !is_affected(p));
+    self
+      .pnp_manifests
+      .as_mut()
+      .retain(|p, _| !is_affected(p) && !is_affected(&p.with_file_name(".pnp.data.json")));
+
+    // A workspace depends on the package.json files of all of its packages, and the
+    // directories containing them. Packages are added and removed rarely, so clear them all.
+    let workspaces = self.workspaces.as_mut();
+    if events.iter().any(|event| {
+      matches!(event, FileSystemEvent::Delete(..))
+        || matches!(event.path().file_name(), Some(name) if name == "package.json" || name == "pnpm-workspace.yaml")
+    }) {
+      workspaces.clear();
+    }
+
+    // A tsconfig.json includes the options of the files it extends, so if any of them
+    // changed, we cannot know which other entries are stale. There are usually few of these.
+    let tsconfigs = self.tsconfigs.as_mut();
+    if tsconfigs.keys().any(|p| is_affected(p)) {
+      tsconfigs.clear();
+    }
+  }
+
+  pub fn read_pnp_manifest(&self, path: &Path) -> Result<&PnpManifest, ResolverError> {
+    if let Some(manifest) = self.pnp_manifests.get(path) {
+      return clone_result(manifest);
+    }
+
+    let read_pnp_manifest = || -> Result<PnpManifest, ResolverError> {
+      // Yarn can be configured to write the state to a separate file rather than inlining it.
+      let data_path = path.with_file_name(".pnp.data.json");
+      if self.fs.is_file(&data_path) {
+        let data = self.fs.read_to_string(&data_path)?;
+        return Ok(PnpManifest::parse(path.to_owned(), Some(data_path), &data)?);
+      }
+
+      let source = self.fs.read_to_string(path)?;
+      let data = PnpManifest::extract_inline_state(&source).ok_or_else(|| JsonError {
+        path: path.to_owned(),
+        line: 0,
+        column: 0,
+        message: "Could not find the serialized runtime state".into(),
+      })?;
+      Ok(PnpManifest::parse(path.to_owned(), None, &data)?)
+    };
+
+    let manifest = self
+      .pnp_manifests
+      .insert(path.to_owned(), Box::new(read_pnp_manifest()));
+
+    clone_result(manifest)
+  }
+
+  pub fn read_import_map(&self, path: &Path) -> Result<&ImportMap, ResolverError> {
+    if let Some(import_map) = self.import_maps.get(path) {
+      return clone_result(import_map);
+    }
+
+    let import_map = self.import_maps.insert(
+      path.to_owned(),
+      Box::new(
+        self
+          .fs
+          .read_to_string(path)
+          .map_err(ResolverError::from)
+          .and_then(|source| ImportMap::parse(&source, path)),
+      ),
+    );
+
+    clone_result(import_map)
+  }
+
+  /// Reads the workspace declared by a pnpm-workspace.yaml or package.json file, and finds its packages.
+  pub fn read_workspace(&self, manifest: &Path) -> Result<&Workspace, ResolverError> {
+    if let Some(workspace) = self.workspaces.get(manifest) {
+      return clone_result(workspace);
+    }
+
+    let workspace = self.workspaces.insert(
+      manifest.to_owned(),
+      Box::new(Workspace::read(self, manifest)),
+    );
+
+    clone_result(workspace)
+  }
+
   pub fn read_tsconfig<'a, F: FnOnce(&mut TsConfigWrapper<'a>) -> Result<(), ResolverError>>(
     &'a self,
     path: &Path,
@@ -173,10 +613,11 @@ impl Cache {
     fn read_tsconfig<'fs, 'a, F: FnOnce(&mut TsConfigWrapper<'a>) -> Result<(), ResolverError>>(
       fs: &'fs dyn FileSystem,
       arena: &Mutex<Arena<Box<str>>>,
+      files: Option<&DashMap<PathBuf, CachedFile, xxhash_rust::xxh3::Xxh3Builder>>,
       path: &Path,
       process: F,
     ) -> Result<TsConfigWrapper<'static>, ResolverError> {
-      let data = read(fs, arena, path)?;
+      let data = read(fs, arena, files, path)?;
       let mut tsconfig =
         TsConfig::parse(path.to_owned(), data).map_err(|e| JsonError::new(path.to_owned(), e))?;
       // Convice the borrow checker that 'a will live as long as self and not 'static.
@@ -187,7 +628,13 @@ impl Cache {
 
     let tsconfig = self.tsconfigs.insert(
       path.to_owned(),
-      Box::new(read_tsconfig(&*self.fs, &self.arena, path, process)),
+      Box::new(read_tsconfig(
+        &*self.fs,
+        &self.arena,
+        self.track_files.then_some(&self.files),
+        path,
+        process,
+      )),
     );
 
     clone_result(tsconfig)
@@ -197,17 +644,227 @@ impl Cache {
 fn read<'fs>(
   fs: &'fs dyn FileSystem,
   arena: &Mutex<Arena<Box<str>>>,
+  files: Option<&DashMap<PathBuf, CachedFile, xxhash_rust::xxh3::Xxh3Builder>>,
   path: &Path,
 ) -> std::io::Result<&'static mut str> {
+  // If files are tracked, use the contents from a snapshot if possible. Otherwise, keep a copy
+  // for the next snapshot, since tsconfig.json files are modified in place when stripping comments.
+  let contents = match files {
+    Some(files) => match files.get(path) {
+      Some(file) => file.contents.to_string(),
+      None => {
+        let contents = fs.read_to_string(path)?;
+        files.insert(path.to_owned(), CachedFile::new(fs, path, contents.clone()));
+        contents
+      }
+    },
+    None => fs.read_to_string(path)?,
+  };
+
   let arena = arena.lock();
-  let data = arena.alloc(fs.read_to_string(path)?.into_boxed_str());
+  let data = arena.alloc(contents.into_boxed_str());
   // The data lives as long as the arena. In public methods, we only vend temporary references.
   Ok(unsafe { &mut *(&mut **data as *mut str) })
 }
 
+/// Returns the cached results for the directory containing a path, and the path's name within it.
+fn dir_entry<'a>(
+  dirs: &'a mut HashMap<PathBuf, CachedDir>,
+  path: &Path,
+) -> Option<(&'a mut CachedDir, String)> {
+  let name = path.file_name()?.to_string_lossy().into_owned();
+  Some((dirs.entry(path.parent()?.to_owned()).or_default(), name))
+}
+
+fn write_entries<T>(
+  writer: &mut SnapshotWriter,
+  entries: &[(String, T)],
+  write: impl Fn(&mut SnapshotWriter, &T),
+) {
+  writer.len(entries.len());
+  for (name, value) in entries {
+    writer.str(name);
+    write(writer, value);
+  }
+}
+
+fn read_entries<T>(
+  reader: &mut SnapshotReader,
+  read: impl Fn(&mut SnapshotReader) -> std::io::Result<T>,
+) -> std::io::Result<Vec<(String, T)>> {
+  (0..reader.len()?)
+    .map(|_| Ok((reader.str()?.to_owned(), read(reader)?)))
+    .collect()
+}
+
 fn clone_result<T, E: Clone>(res: &Result<T, E>) -> Result<&T, E> {
   match res {
     Ok(v) => Ok(v),
     Err(err) => Err(err.clone()),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use assert_fs::prelude::*;
+
+  use super::*;
+  use crate::OsFileSystem;
+
+  #[test]
+  fn test_invalidate() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("package.json").write_str(r#"{"name": "a"}"#)?;
+    let package_path = dir.child("package.json").path().to_owned();
+    let new_path = dir.child("foo/bar.js").path().to_owned();
+
+    let mut cache = Cache::new(Arc::new(OsFileSystem));
+    assert_eq!(
+      cache
+        .read_package(Cow::Borrowed(&package_path))
+        .unwrap()
+        .name,
+      "a"
+    );
+    assert!(!cache.is_file(&new_path));
+    assert!(!cache.is_dir(new_path.parent().unwrap()));
+    assert!(!cache.read_dir(dir.path()).unwrap().contains(&"foo".into()));
+
+    dir.child("package.json").write_str(r#"{"name": "b"}"#)?;
+    dir.child("foo/bar.js").write_str("")?;
+    assert_eq!(
+      cache
+        .read_package(Cow::Borrowed(&package_path))
+        .unwrap()
+        .name,
+      "a"
+    );
+    assert!(!cache.is_file(&new_path));
+
+    cache.invalidate(&package_path);
+    assert_eq!(
+      cache
+        .read_package(Cow::Borrowed(&package_path))
+        .unwrap()
+        .name,
+      "b"
+    );
+    assert!(!cache.is_file(&new_path));
+
+    cache.invalidate_events(&[FileSystemEvent::Create(new_path.clone())]);
+    assert!(cache.is_file(&new_path));
+    assert!(cache.is_dir(new_path.parent().unwrap()));
+    assert!(cache.read_dir(dir.path()).unwrap().contains(&"foo".into()));
+
+    std::fs::remove_dir_all(dir.child("foo").path())?;
+    cache.invalidate_events(&[FileSystemEvent::Delete(dir.child("foo").path().to_owned())]);
+    assert!(!cache.is_file(&new_path));
+    assert!(!cache.is_dir(new_path.parent().unwrap()));
+
+    Ok(())
+  }
+
+  /// Counts the files that are read and stat calls, to check which ones are loaded from a snapshot.
+  struct CountingFileSystem {
+    reads: std::sync::atomic::AtomicUsize,
+    stats: std::sync::atomic::AtomicUsize,
+  }
+
+  impl FileSystem for CountingFileSystem {
+    fn canonicalize(
+      &self,
+      path: &Path,
+      cache: &FileSystemRealPathCache,
+    ) -> std::io::Result<PathBuf> {
+      OsFileSystem.canonicalize(path, cache)
+    }
+
+    fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+      self.reads.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+      OsFileSystem.read_to_string(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+      self.stats.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+      OsFileSystem.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+      self.stats.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+      OsFileSystem.is_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> std::io::Result<FileMetadata> {
+      OsFileSystem.metadata(path)
+    }
+  }
+
+  #[test]
+  fn test_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("a/package.json").write_str(r#"{"name": "a"}"#)?;
+    dir.child("b/package.json").write_str(r#"{"name": "b"}"#)?;
+    dir.child("c/package.json").write_str(r#"{"name": "c"}"#)?;
+    let path = |name: &str| dir.child(name).path().to_owned();
+
+    let cache = Cache::with_snapshots(Arc::new(OsFileSystem));
+    for name in ["a", "b", "c"] {
+      let package_path = path(&format!("{}/package.json", name));
+      assert_eq!(
+        cache.read_package(Cow::Owned(package_path)).unwrap().name,
+        name
+      );
+    }
+    assert!(cache.is_file(&path("a/package.json")));
+    assert!(!cache.is_file(&path("a/index.js")));
+    assert!(cache.is_dir(&path("a")));
+    assert!(cache.is_file(&path("c/package.json")));
+    let snapshot = cache.write_snapshot();
+
+    dir.child("b/package.json").write_str(r#"{"name": "b2"}"#)?;
+    std::fs::remove_file(path("c/package.json"))?;
+
+    let fs = Arc::new(CountingFileSystem {
+      reads: Default::default(),
+      stats: Default::default(),
+    });
+    let cache = Cache::from_snapshot(fs.clone(), &snapshot)?;
+    // Only the changed file is read again, to compare its contents.
+    assert_eq!(fs.reads.load(std::sync::atomic::Ordering::SeqCst), 1);
+    // Stat results are kept for directories that did not change.
+    assert!(!cache.is_file(&path("a/index.js")));
+    assert!(cache.is_dir(&path("a")));
+    assert_eq!(fs.stats.load(std::sync::atomic::Ordering::SeqCst), 0);
+    assert!(!cache.is_file(&path("c/package.json")));
+    assert_eq!(fs.stats.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert_eq!(
+      cache
+        .read_package(Cow::Owned(path("a/package.json")))
+        .unwrap()
+        .name,
+      "a"
+    );
+    assert_eq!(
+      cache
+        .read_package(Cow::Owned(path("b/package.json")))
+        .unwrap()
+        .name,
+      "b2"
+    );
+    assert!(cache
+      .read_package(Cow::Owned(path("c/package.json")))
+      .is_err());
+    assert_eq!(fs.reads.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+    assert!(Cache::from_snapshot(fs, &snapshot[..snapshot.len() - 1]).is_err());
+
+    // Files are only tracked for snapshots if requested.
+    let cache = Cache::new(Arc::new(OsFileSystem));
+    cache
+      .read_package(Cow::Owned(path("a/package.json")))
+      .unwrap();
+    assert!(cache.files.is_empty());
+    assert!(cache.dirs.is_empty());
+    Ok(())
+  }
+}
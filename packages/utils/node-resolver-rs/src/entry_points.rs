@@ -0,0 +1,152 @@
+use std::{
+  collections::HashSet,
+  path::{Path, PathBuf},
+};
+
+use crate::{
+  package_json::{ExportsTarget, PackageJson},
+  path::resolve_path,
+  specifier::{decode_path, SpecifierType},
+  Cache, ExportsCondition, PackageJsonError,
+};
+
+/// An entry point of a package: a subpath and set of conditions in the "exports" field, and the file it resolves to.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportsEntry {
+  /// The exported subpath, e.g. "." or "./features/foo". Patterns are expanded to the files they match.
+  pub subpath: String,
+  /// The conditions that select the entry, outermost first, e.g. ["browser", "import"].
+  pub conditions: Vec<String>,
+  /// The resolved file, which may not exist unless the subpath was expanded from a pattern.
+  pub path: PathBuf,
+}
+
+/// Enumerates the entry points of a package. Each entry is checked by resolving its subpath with its
+/// conditions, so targets that are shadowed by an earlier condition or fallback, or by a null
+/// pattern, are excluded.
+pub(crate) fn exports_entries(
+  cache: &Cache,
+  package: &PackageJson,
+) -> Result<Vec<ExportsEntry>, PackageJsonError> {
+  let mut entries = Vec::new();
+  for target in package.exports_targets()? {
+    let mut conditions = ExportsCondition::empty();
+    let mut custom_conditions = Vec::new();
+    for condition in &target.conditions {
+      match ExportsCondition::try_from(*condition) {
+        Ok(condition) => conditions |= condition,
+        Err(()) => custom_conditions.push(condition.to_string()),
+      }
+    }
+
+    for (subpath, path) in expand_target(cache, package, &target) {
+      let exported = package.resolve_package_exports(
+        subpath.strip_prefix("./").unwrap_or(""),
+        conditions,
+        &custom_conditions,
+      );
+      if exported.as_ref() != Ok(&path) {
+        continue;
+      }
+
+      let entry = ExportsEntry {
+        subpath,
+        conditions: target.conditions.iter().map(|c| c.to_string()).collect(),
+        path,
+      };
+      if !entries.contains(&entry) {
+        entries.push(entry);
+      }
+    }
+  }
+
+  Ok(entries)
+}
+
+/// Returns the subpaths and files for a target. A pattern is expanded to the files in the package
+/// that its target matches, and patterns mapping to a single file are skipped.
+fn expand_target(
+  cache: &Cache,
+  package: &PackageJson,
+  target: &ExportsTarget,
+) -> Vec<(String, PathBuf)> {
+  let resolve =
+    |target: &str| resolve_path(&package.path, decode_path(target, SpecifierType::Esm).0);
+  let (subpath_base, subpath_trailer) = match target.subpath.split_once('*') {
+    Some(parts) => parts,
+    None => return vec![(target.subpath.clone(), resolve(target.target))],
+  };
+
+  let (target_base, target_trailer) = match target.target.split_once('*') {
+    Some(parts) => parts,
+    None => return Vec::new(),
+  };
+
+  // Patterns match across directories, so list every file below the directory of the target base.
+  let dir = &target_base[..target_base.rfind('/').unwrap() + 1];
+  let mut files = Vec::new();
+  list_files(
+    cache,
+    &package.path.parent().unwrap().join(&dir[2..]),
+    dir,
+    &mut HashSet::new(),
+    &mut files,
+  );
+
+  files
+    .into_iter()
+    .filter_map(|file| {
+      let capture = file
+        .strip_prefix(target_base)?
+        .strip_suffix(target_trailer)?;
+      if capture.is_empty() || target.target.replace('*', capture) != file {
+        return None;
+      }
+
+      Some((
+        format!("{}{}{}", subpath_base, capture, subpath_trailer),
+        resolve(&file),
+      ))
+    })
+    .collect()
+}
+
+/// Lists the files below a directory as relative paths starting with a prefix, e.g. "./dist/foo.js".
+/// Symlinked directories are followed, but each real directory is only listed once to avoid cycles.
+fn list_files(
+  cache: &Cache,
+  dir: &Path,
+  prefix: &str,
+  visited: &mut HashSet<PathBuf>,
+  files: &mut Vec<String>,
+) {
+  match cache.canonicalize(dir) {
+    Ok(realpath) => {
+      if !visited.insert(realpath) {
+        return;
+      }
+    }
+    Err(_) => return,
+  }
+
+  let mut names = match cache.read_dir(dir) {
+    Some(names) => names.to_vec(),
+    None => return,
+  };
+  names.sort();
+
+  for name in names {
+    if name == "node_modules" {
+      continue;
+    }
+
+    let path = dir.join(&name);
+    let relative = format!("{}{}", prefix, name);
+    if cache.is_dir(&path) {
+      list_files(cache, &path, &format!("{}/", relative), visited, files);
+    } else if cache.is_file(&path) {
+      files.push(relative);
+    }
+  }
+}
@@ -1,5 +1,5 @@
-use crate::PackageJsonError;
 use crate::{cache::JsonError, specifier::SpecifierError};
+use crate::{ImportMapError, PackageJsonError};
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -13,9 +13,15 @@ pub enum ResolverError {
   FileNotFound {
     relative: PathBuf,
     from: PathBuf,
+    /// Similarly named files that exist, relative to `from`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<PathBuf>,
   },
   ModuleNotFound {
     module: String,
+    /// Similarly named packages that are installed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<String>,
   },
   ModuleEntryNotFound {
     module: String,
@@ -27,6 +33,15 @@ pub enum ResolverError {
     module: String,
     path: PathBuf,
     package_path: PathBuf,
+    /// Specifiers for similar subpaths that the package exports, or similarly named files in the package.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<String>,
+  },
+  /// The casing of a resolved path differs from the file on disk. This only resolves
+  /// on case-insensitive file systems, e.g. on macOS and Windows.
+  IncorrectCase {
+    path: PathBuf,
+    actual_path: PathBuf,
   },
   JsonError(JsonError),
   IOError(IOError),
@@ -38,6 +53,12 @@ pub enum ResolverError {
   PackageJsonNotFound {
     from: PathBuf,
   },
+  /// The specifier matched an import map entry that cannot map it.
+  ImportMapError {
+    import_map: PathBuf,
+    key: String,
+    error: ImportMapError,
+  },
   InvalidSpecifier(SpecifierError),
   TsConfigExtendsNotFound {
     tsconfig: PathBuf,
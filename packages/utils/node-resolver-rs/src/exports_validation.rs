@@ -0,0 +1,573 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use percent_encoding::percent_decode_str;
+
+/// A structural problem in the "exports" or "imports" field of a package.json file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportsDiagnostic {
+  pub kind: ExportsDiagnosticKind,
+  pub message: String,
+  pub path: PathBuf,
+  /// The line and column of the offending key or value, starting at 1.
+  pub line: usize,
+  pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ExportsDiagnosticKind {
+  /// A value that is not a string, array, object or null.
+  InvalidType,
+  /// An "exports" object with both subpath keys, starting with ".", and condition keys.
+  MixedKeys,
+  /// A subpath or import key that is malformed, e.g. "./foo/*/*" or "#".
+  InvalidKey,
+  /// A condition key that is not allowed, e.g. a subpath within a condition map, or an array index.
+  InvalidCondition,
+  /// A target that does not start with "./", or an import target that is an absolute or parent path.
+  InvalidTarget,
+  /// A target with an empty, ".", ".." or "node_modules" segment.
+  InvalidTargetSegment,
+  /// A "default" condition followed by other conditions, which can never match.
+  DefaultNotLast,
+}
+
+/// Validates the "exports" and "imports" fields of a package.json file, and returns all structural
+/// problems that Node would reject or ignore when resolving them. Each problem has its location in
+/// the source, so this takes the contents of the file rather than a parsed package.json.
+pub fn validate_exports(path: &std::path::Path, source: &str) -> Vec<ExportsDiagnostic> {
+  let mut validator = Validator {
+    path,
+    diagnostics: Vec::new(),
+  };
+
+  let root = match JsonParser::new(source).parse() {
+    Some(root) => root,
+    None => return Vec::new(),
+  };
+
+  if let Value::Object(fields) = &root.value {
+    for (key, value) in fields {
+      match key.name.as_ref() {
+        "exports" => validator.exports(value),
+        "imports" => validator.imports(value),
+        _ => {}
+      }
+    }
+  }
+
+  validator.diagnostics
+}
+
+struct Validator<'a> {
+  path: &'a std::path::Path,
+  diagnostics: Vec<ExportsDiagnostic>,
+}
+
+impl<'a> Validator<'a> {
+  fn report(&mut self, kind: ExportsDiagnosticKind, location: Location, message: String) {
+    self.diagnostics.push(ExportsDiagnostic {
+      kind,
+      message,
+      path: self.path.to_owned(),
+      line: location.line,
+      column: location.column,
+    });
+  }
+
+  fn exports(&mut self, exports: &Node) {
+    let fields = match &exports.value {
+      Value::Object(fields) => fields,
+      _ => return self.target(exports, false),
+    };
+
+    let is_subpath = |key: &Key| key.name.starts_with('.');
+    let subpaths = match fields.first() {
+      Some((first, _)) => is_subpath(first),
+      None => return,
+    };
+
+    if let Some((key, _)) = fields.iter().find(|(key, _)| is_subpath(key) != subpaths) {
+      self.report(
+        ExportsDiagnosticKind::MixedKeys,
+        key.location,
+        format!(
+          "\"exports\" cannot contain both subpaths and conditions, but found \"{}\"",
+          key.name
+        ),
+      );
+      return;
+    }
+
+    if !subpaths {
+      return self.conditions(fields, false);
+    }
+
+    for (key, value) in fields {
+      if key.name != "." && !key.name.starts_with("./") {
+        self.report(
+          ExportsDiagnosticKind::InvalidKey,
+          key.location,
+          format!(
+            "Subpath \"{}\" must be \".\" or start with \"./\"",
+            key.name
+          ),
+        );
+      } else {
+        self.pattern_key(key);
+      }
+
+      self.target(value, false);
+    }
+  }
+
+  fn imports(&mut self, imports: &Node) {
+    let fields = match &imports.value {
+      Value::Object(fields) => fields,
+      Value::Null => return,
+      _ => {
+        return self.report(
+          ExportsDiagnosticKind::InvalidType,
+          imports.location,
+          "\"imports\" must be an object".into(),
+        )
+      }
+    };
+
+    for (key, value) in fields {
+      if !key.name.starts_with('#') || key.name == "#" || key.name.starts_with("#/") {
+        self.report(
+          ExportsDiagnosticKind::InvalidKey,
+          key.location,
+          format!(
+            "Import \"{}\" must start with \"#\", followed by a name that does not start with \"/\"",
+            key.name
+          ),
+        );
+      } else {
+        self.pattern_key(key);
+      }
+
+      self.target(value, true);
+    }
+  }
+
+  fn pattern_key(&mut self, key: &Key) {
+    if key.name.matches('*').count() > 1 {
+      self.report(
+        ExportsDiagnosticKind::InvalidKey,
+        key.location,
+        format!(
+          "Pattern \"{}\" can only contain a single \"*\", so it is ignored",
+          key.name
+        ),
+      );
+    }
+  }
+
+  fn conditions(&mut self, fields: &[(Key, Node)], is_imports: bool) {
+    for (index, (key, value)) in fields.iter().enumerate() {
+      if key.name.starts_with('.') || key.name.starts_with('#') {
+        self.report(
+          ExportsDiagnosticKind::InvalidCondition,
+          key.location,
+          format!(
+            "Condition maps can only contain conditions, but found \"{}\"",
+            key.name
+          ),
+        );
+      } else if key.name.parse::<u32>().is_ok() {
+        self.report(
+          ExportsDiagnosticKind::InvalidCondition,
+          key.location,
+          format!("Condition \"{}\" must not be an array index", key.name),
+        );
+      } else if key.name == "default" && index + 1 < fields.len() {
+        self.report(
+          ExportsDiagnosticKind::DefaultNotLast,
+          key.location,
+          "The \"default\" condition must be last, or the conditions after it never match".into(),
+        );
+      }
+
+      self.target(value, is_imports);
+    }
+  }
+
+  fn target(&mut self, target: &Node, is_imports: bool) {
+    match &target.value {
+      Value::String(s) => self.string_target(s, target.location, is_imports),
+      Value::Array(items) => {
+        for item in items {
+          self.target(item, is_imports);
+        }
+      }
+      Value::Object(fields) => self.conditions(fields, is_imports),
+      Value::Null => {}
+      Value::Other => self.report(
+        ExportsDiagnosticKind::InvalidType,
+        target.location,
+        "Targets must be a string, array, object or null".into(),
+      ),
+    }
+  }
+
+  fn string_target(&mut self, target: &str, location: Location, is_imports: bool) {
+    let rest = match target.strip_prefix("./") {
+      Some(rest) => rest,
+      None => {
+        // Imports can also map to packages, but not to paths outside the package.
+        if !is_imports || target.starts_with("../") || target.starts_with('/') || is_url(target) {
+          self.report(
+            ExportsDiagnosticKind::InvalidTarget,
+            location,
+            format!("Target \"{}\" must start with \"./\"", target),
+          );
+        }
+        return;
+      }
+    };
+
+    let decoded = percent_decode_str(rest).decode_utf8_lossy();
+    if let Some(segment) = decoded
+      .split(['/', '\\'])
+      .find(|s| matches!(*s, "" | "." | "..") || s.eq_ignore_ascii_case("node_modules"))
+    {
+      self.report(
+        ExportsDiagnosticKind::InvalidTargetSegment,
+        location,
+        format!(
+          "Target \"{}\" must not contain {} segments",
+          target,
+          if segment.is_empty() {
+            "empty".into()
+          } else {
+            format!("\"{}\"", segment)
+          }
+        ),
+      );
+    }
+  }
+}
+
+fn is_url(target: &str) -> bool {
+  match target.split_once(':') {
+    Some((scheme, _)) => {
+      scheme.len() > 1
+        && scheme
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    }
+    None => false,
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Location {
+  line: usize,
+  column: usize,
+}
+
+struct Key<'s> {
+  name: Cow<'s, str>,
+  location: Location,
+}
+
+struct Node<'s> {
+  value: Value<'s>,
+  location: Location,
+}
+
+enum Value<'s> {
+  Null,
+  String(Cow<'s, str>),
+  Array(Vec<Node<'s>>),
+  Object(Vec<(Key<'s>, Node<'s>)>),
+  /// A boolean or number.
+  Other,
+}
+
+/// A minimal JSON parser that records the location of each key and value, which serde_json does not.
+/// The source has already been parsed by serde_json, so this gives up on invalid input rather than
+/// reporting errors.
+struct JsonParser<'s> {
+  source: &'s str,
+  offset: usize,
+  line: usize,
+  line_start: usize,
+}
+
+impl<'s> JsonParser<'s> {
+  fn new(source: &'s str) -> Self {
+    JsonParser {
+      source,
+      offset: 0,
+      line: 1,
+      line_start: 0,
+    }
+  }
+
+  fn parse(&mut self) -> Option<Node<'s>> {
+    self.skip_whitespace();
+    self.value()
+  }
+
+  fn location(&self) -> Location {
+    Location {
+      line: self.line,
+      column: self.source[self.line_start..self.offset].chars().count() + 1,
+    }
+  }
+
+  fn peek(&self) -> Option<u8> {
+    self.source.as_bytes().get(self.offset).copied()
+  }
+
+  fn skip_whitespace(&mut self) {
+    while let Some(c) = self.peek() {
+      match c {
+        b'\n' => {
+          self.offset += 1;
+          self.line += 1;
+          self.line_start = self.offset;
+        }
+        b' ' | b'\t' | b'\r' => self.offset += 1,
+        _ => break,
+      }
+    }
+  }
+
+  fn expect(&mut self, c: u8) -> Option<()> {
+    self.skip_whitespace();
+    if self.peek()? == c {
+      self.offset += 1;
+      Some(())
+    } else {
+      None
+    }
+  }
+
+  fn value(&mut self) -> Option<Node<'s>> {
+    let location = self.location();
+    let value = match self.peek()? {
+      b'{' => {
+        self.offset += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek()? == b'}' {
+          self.offset += 1;
+        } else {
+          loop {
+            self.skip_whitespace();
+            let location = self.location();
+            let name = self.string()?;
+            self.expect(b':')?;
+            self.skip_whitespace();
+            fields.push((Key { name, location }, self.value()?));
+            self.skip_whitespace();
+            match self.peek()? {
+              b',' => self.offset += 1,
+              b'}' => {
+                self.offset += 1;
+                break;
+              }
+              _ => return None,
+            }
+          }
+        }
+        Value::Object(fields)
+      }
+      b'[' => {
+        self.offset += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek()? == b']' {
+          self.offset += 1;
+        } else {
+          loop {
+            self.skip_whitespace();
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek()? {
+              b',' => self.offset += 1,
+              b']' => {
+                self.offset += 1;
+                break;
+              }
+              _ => return None,
+            }
+          }
+        }
+        Value::Array(items)
+      }
+      b'"' => Value::String(self.string()?),
+      _ => {
+        let len = self.source[self.offset..]
+          .find(|c: char| matches!(c, ',' | '}' | ']') || c.is_ascii_whitespace())
+          .unwrap_or(self.source.len() - self.offset);
+        let literal = &self.source[self.offset..self.offset + len];
+        self.offset += len;
+        match literal {
+          "null" => Value::Null,
+          "" => return None,
+          _ => Value::Other,
+        }
+      }
+    };
+
+    Some(Node { value, location })
+  }
+
+  fn string(&mut self) -> Option<Cow<'s, str>> {
+    if self.peek()? != b'"' {
+      return None;
+    }
+
+    let start = self.offset + 1;
+    let mut end = start;
+    let mut escaped = false;
+    loop {
+      match *self.source.as_bytes().get(end)? {
+        b'"' => break,
+        b'\\' => {
+          escaped = true;
+          end += 2;
+        }
+        _ => end += 1,
+      }
+    }
+
+    self.offset = end + 1;
+    let raw = &self.source[start..end];
+    if !escaped {
+      return Some(Cow::Borrowed(raw));
+    }
+
+    // Let serde_json handle escape sequences, including surrogate pairs.
+    serde_json::from_str(&self.source[start - 1..=end])
+      .ok()
+      .map(Cow::Owned)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::Path;
+
+  fn validate(source: &str) -> Vec<(ExportsDiagnosticKind, usize, usize)> {
+    validate_exports(Path::new("/foo/package.json"), source)
+      .into_iter()
+      .map(|d| (d.kind, d.line, d.column))
+      .collect()
+  }
+
+  #[test]
+  fn test_valid() {
+    assert_eq!(
+      validate(
+        r##"{
+  "name": "foo",
+  "exports": {
+    ".": {"types": "./index.d.ts", "import": "./index.mjs", "default": "./index.js"},
+    "./features/*.js": ["./src/features/*.js", null],
+    "./internal/*": null,
+    "./package.json": "./package.json"
+  },
+  "imports": {
+    "#dep": {"node": "dep-node", "default": "./dep.js"},
+    "#utils/*": "./src/utils/*.js"
+  }
+}"##
+      ),
+      vec![]
+    );
+    assert_eq!(validate(r#"{"exports": "./index.js"}"#), vec![]);
+    assert_eq!(
+      validate(r#"{"exports": {"import": "./index.mjs", "require": "./index.cjs"}}"#),
+      vec![]
+    );
+  }
+
+  #[test]
+  fn test_invalid() {
+    use ExportsDiagnosticKind::*;
+
+    assert_eq!(
+      validate(
+        r#"{
+  "exports": {
+    ".": "./index.js",
+    "import": "./index.mjs"
+  }
+}"#
+      ),
+      vec![(MixedKeys, 4, 5)]
+    );
+    assert_eq!(
+      validate(
+        r#"{
+  "exports": {
+    ".": {
+      "default": "./index.js",
+      "import": "./index.mjs"
+    },
+    "./foo": "foo.js",
+    "./bar": "./lib/../bar.js",
+    "./baz": "./node_modules/baz/index.js",
+    "./qux": "./%2e%2e/qux.js",
+    "./a/*/*": "./a/*/*.js",
+    ".foo": "./foo.js",
+    "./nested": {"./foo": "./foo.js", "0": "./zero.js"},
+    "./number": 1,
+    "./url": "https://example.com/foo.js"
+  }
+}"#
+      ),
+      vec![
+        (DefaultNotLast, 4, 7),
+        (InvalidTarget, 7, 14),
+        (InvalidTargetSegment, 8, 14),
+        (InvalidTargetSegment, 9, 14),
+        (InvalidTargetSegment, 10, 14),
+        (InvalidKey, 11, 5),
+        (InvalidKey, 12, 5),
+        (InvalidCondition, 13, 18),
+        (InvalidCondition, 13, 39),
+        (InvalidType, 14, 17),
+        (InvalidTarget, 15, 14),
+      ]
+    );
+    assert_eq!(
+      validate(
+        r##"{"imports": {"#": "./a.js", "#/b": "./b.js", "c": "./c.js", "#d": "../d.js", "#e": "/e.js", "#f": "f"}}"##
+      ),
+      vec![
+        (InvalidKey, 1, 14),
+        (InvalidKey, 1, 29),
+        (InvalidKey, 1, 46),
+        (InvalidTarget, 1, 67),
+        (InvalidTarget, 1, 84),
+      ]
+    );
+    assert_eq!(
+      validate(r#"{"imports": "./a.js"}"#),
+      vec![(InvalidType, 1, 13)]
+    );
+  }
+
+  #[test]
+  fn test_escaped_keys() {
+    let diagnostics = validate_exports(
+      Path::new("/foo/package.json"),
+      "{\"exports\": {\"\\u002e/f\\u00f6\\u00f6\": \"f\u{f6}\u{f6}.js\"}}",
+    );
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, ExportsDiagnosticKind::InvalidTarget);
+    assert_eq!(diagnostics[0].column, 38);
+    assert_eq!(
+      diagnostics[0].message,
+      "Target \"f\u{f6}\u{f6}.js\" must start with \"./\""
+    );
+  }
+}
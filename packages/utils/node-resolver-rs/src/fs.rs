@@ -10,8 +10,40 @@ use dashmap::DashMap;
 pub trait FileSystem: Send + Sync {
   fn canonicalize(&self, path: &Path, cache: &FileSystemRealPathCache) -> Result<PathBuf>;
   fn read_to_string(&self, path: &Path) -> Result<String>;
+  /// Reads a binary file, e.g. a zip archive in the Yarn cache.
+  fn read(&self, path: &Path) -> Result<Vec<u8>> {
+    self.read_to_string(path).map(String::into_bytes)
+  }
+  /// Reads up to `len` bytes of a binary file, starting at `offset`. This is used to read single
+  /// entries of zip archives without loading the whole archive.
+  fn read_range(&self, path: &Path, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut data = self.read(path)?;
+    let start = (offset as usize).min(data.len());
+    data.truncate(start.saturating_add(len).min(data.len()));
+    data.drain(..start);
+    Ok(data)
+  }
   fn is_file(&self, path: &Path) -> bool;
   fn is_dir(&self, path: &Path) -> bool;
+  /// Returns the names of the entries in a directory. This is used for suggestions in errors,
+  /// and to check the casing of paths when `Flags::CASE_SENSITIVE` is enabled.
+  fn read_dir(&self, _path: &Path) -> Result<Vec<String>> {
+    Err(std::io::ErrorKind::Unsupported.into())
+  }
+  /// Returns the modification time and size of a file. This is used to check whether files
+  /// in a cache snapshot changed without reading them. Otherwise, their contents are compared.
+  fn metadata(&self, _path: &Path) -> Result<FileMetadata> {
+    Err(std::io::ErrorKind::Unsupported.into())
+  }
+  /// Called when a file changed, so that file systems that cache information about files can update it.
+  fn invalidate(&self, _path: &Path) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetadata {
+  /// The modification time, in nanoseconds since the Unix epoch.
+  pub modified: u64,
+  pub len: u64,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -31,6 +63,20 @@ impl FileSystem for OsFileSystem {
     std::fs::read_to_string(path)
   }
 
+  fn read(&self, path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path)
+  }
+
+  fn read_range(&self, path: &Path, offset: u64, len: usize) -> Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut data)?;
+    Ok(data)
+  }
+
   fn is_file(&self, path: &Path) -> bool {
     path.is_file()
   }
@@ -38,4 +84,22 @@ impl FileSystem for OsFileSystem {
   fn is_dir(&self, path: &Path) -> bool {
     path.is_dir()
   }
+
+  fn read_dir(&self, path: &Path) -> Result<Vec<String>> {
+    std::fs::read_dir(path)?
+      .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+      .collect()
+  }
+
+  fn metadata(&self, path: &Path) -> Result<FileMetadata> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata
+      .modified()?
+      .duration_since(std::time::UNIX_EPOCH)
+      .map_err(|_| std::io::ErrorKind::InvalidData)?;
+    Ok(FileMetadata {
+      modified: modified.as_nanos() as u64,
+      len: metadata.len(),
+    })
+  }
 }
@@ -0,0 +1,336 @@
+use std::{
+  borrow::Cow,
+  collections::HashMap,
+  path::{Component, Path, PathBuf},
+};
+
+use crate::{
+  cache::JsonError,
+  path::{normalize_path, resolve_path},
+  specifier::{Specifier, SpecifierType},
+  Flags, ResolverError,
+};
+
+/// An import map, which remaps specifiers before they are resolved, as in browsers and Deno.
+/// Relative paths in the map are resolved against the directory containing it, which
+/// is also the root for paths starting with `/`.
+/// See https://github.com/WICG/import-maps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportMap {
+  /// The path of the import map file.
+  pub path: PathBuf,
+  imports: Vec<ImportMapEntry>,
+  /// Scoped imports, most specific scope first.
+  scopes: Vec<(PathBuf, Vec<ImportMapEntry>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ImportMapEntry {
+  key: String,
+  specifier: ImportMapSpecifier,
+  /// Whether the key ends with a slash, and therefore matches any specifier that starts with it.
+  prefix: bool,
+  /// The target, or why the entry blocks the specifiers it matches.
+  target: Result<ImportMapTarget, ImportMapError>,
+}
+
+/// A normalized specifier, which is matched against the keys of an import map.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ImportMapSpecifier {
+  /// A relative or absolute path, resolved to an absolute path.
+  Path(PathBuf),
+  /// A bare specifier or URL.
+  Specifier(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ImportMapTarget {
+  Path(PathBuf),
+  Url(String),
+}
+
+/// Why an import map entry could not map a specifier. As in browsers, this is an error
+/// rather than falling back to resolving the specifier without the import map.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub enum ImportMapError {
+  /// The value of the entry is null.
+  NullEntry,
+  /// The value of the entry is not a valid path or URL, e.g. a bare specifier, or does not end
+  /// with a slash although the key does.
+  InvalidTarget,
+  /// The specifier backtracks above the target of the entry, e.g. "react/../../secret".
+  BacktrackingSpecifier,
+}
+
+#[derive(serde::Deserialize)]
+struct ImportMapJson {
+  #[serde(default)]
+  imports: HashMap<String, serde_json::Value>,
+  #[serde(default)]
+  scopes: HashMap<String, HashMap<String, serde_json::Value>>,
+}
+
+impl ImportMap {
+  /// Parses an import map. Entries with invalid keys are ignored, while entries that are null
+  /// or have an invalid value block the specifiers they match.
+  pub fn parse(source: &str, path: &Path) -> Result<ImportMap, ResolverError> {
+    let json: ImportMapJson = serde_json::from_str(source)
+      .map_err(|err| ResolverError::JsonError(JsonError::new(path.to_owned(), err)))?;
+
+    let mut scopes: Vec<_> = json
+      .scopes
+      .iter()
+      .filter_map(|(scope, imports)| match parse_specifier(scope, path)? {
+        ImportMapSpecifier::Path(scope) => Some((scope, parse_entries(imports, path))),
+        // Scopes that are URLs cannot contain any files.
+        ImportMapSpecifier::Specifier(_) => None,
+      })
+      .collect();
+    scopes.sort_by_key(|(scope, _)| std::cmp::Reverse(scope.as_os_str().len()));
+
+    Ok(ImportMap {
+      path: path.to_owned(),
+      imports: parse_entries(&json.imports, path),
+      scopes,
+    })
+  }
+
+  /// Maps a specifier imported from the given file. Returns the matched key and the target,
+  /// or an error if the matched entry is blocked.
+  pub(crate) fn resolve(
+    &self,
+    specifier: &ImportMapSpecifier,
+    from: &Path,
+  ) -> Option<(&str, Result<ImportMapTarget, ImportMapError>)> {
+    for (scope, imports) in &self.scopes {
+      if from.starts_with(scope) {
+        if let Some(res) = resolve_entries(imports, specifier) {
+          return Some(res);
+        }
+      }
+    }
+
+    resolve_entries(&self.imports, specifier)
+  }
+}
+
+/// Parses the entries of "imports" or a scope, sorted so that longer keys are matched first.
+fn parse_entries(imports: &HashMap<String, serde_json::Value>, path: &Path) -> Vec<ImportMapEntry> {
+  let mut entries: Vec<_> = imports
+    .iter()
+    .filter_map(|(key, value)| {
+      let prefix = key.ends_with('/');
+      Some(ImportMapEntry {
+        key: key.clone(),
+        specifier: parse_specifier(key, path)?,
+        prefix,
+        target: parse_target(value, prefix, path),
+      })
+    })
+    .collect();
+
+  entries.sort_by(|a, b| {
+    b.key
+      .len()
+      .cmp(&a.key.len())
+      .then_with(|| a.key.cmp(&b.key))
+  });
+  entries
+}
+
+fn parse_specifier(specifier: &str, path: &Path) -> Option<ImportMapSpecifier> {
+  // Unlike file: URLs, absolute paths are relative to the directory of the import map.
+  if let Some(specifier) = specifier.strip_prefix('/') {
+    return Some(ImportMapSpecifier::Path(resolve_path(path, specifier)));
+  }
+
+  let (parsed, _) = Specifier::parse(specifier, SpecifierType::Esm, Flags::empty()).ok()?;
+  Some(match parsed {
+    Specifier::Relative(specifier) => ImportMapSpecifier::Path(resolve_path(path, specifier)),
+    Specifier::Absolute(specifier) => ImportMapSpecifier::Path(specifier.into_owned()),
+    Specifier::Builtin(builtin) => ImportMapSpecifier::Specifier(builtin.into_owned()),
+    _ => ImportMapSpecifier::Specifier(specifier.to_owned()),
+  })
+}
+
+fn parse_target(
+  value: &serde_json::Value,
+  prefix: bool,
+  path: &Path,
+) -> Result<ImportMapTarget, ImportMapError> {
+  let value = match value {
+    serde_json::Value::Null => return Err(ImportMapError::NullEntry),
+    serde_json::Value::String(value) => value,
+    _ => return Err(ImportMapError::InvalidTarget),
+  };
+
+  if prefix && !value.ends_with('/') {
+    return Err(ImportMapError::InvalidTarget);
+  }
+
+  match parse_specifier(value, path) {
+    Some(ImportMapSpecifier::Path(path)) => Ok(ImportMapTarget::Path(path)),
+    Some(ImportMapSpecifier::Specifier(value)) if value.contains(':') => {
+      Ok(ImportMapTarget::Url(value))
+    }
+    // Bare specifiers are not valid targets.
+    _ => Err(ImportMapError::InvalidTarget),
+  }
+}
+
+fn resolve_entries<'a>(
+  entries: &'a [ImportMapEntry],
+  specifier: &ImportMapSpecifier,
+) -> Option<(&'a str, Result<ImportMapTarget, ImportMapError>)> {
+  for entry in entries {
+    let remainder: Cow<'_, str> = match (&entry.specifier, specifier) {
+      (ImportMapSpecifier::Path(key), ImportMapSpecifier::Path(path)) => {
+        if entry.prefix {
+          match path.strip_prefix(key) {
+            Ok(remainder) => url_path(remainder).into(),
+            Err(_) => continue,
+          }
+        } else if key == path {
+          "".into()
+        } else {
+          continue;
+        }
+      }
+      (ImportMapSpecifier::Specifier(key), ImportMapSpecifier::Specifier(specifier)) => {
+        if entry.prefix {
+          match specifier.strip_prefix(key.as_str()) {
+            Some(remainder) => remainder.into(),
+            None => continue,
+          }
+        } else if key == specifier {
+          "".into()
+        } else {
+          continue;
+        }
+      }
+      _ => continue,
+    };
+
+    let target = match &entry.target {
+      Ok(ImportMapTarget::Path(target)) => {
+        let path = normalize_path(&target.join(remainder.as_ref()));
+        // The remainder may not backtrack above the target, e.g. "lodash/../foo".
+        if path.starts_with(target) {
+          Ok(ImportMapTarget::Path(path))
+        } else {
+          Err(ImportMapError::BacktrackingSpecifier)
+        }
+      }
+      Ok(ImportMapTarget::Url(target)) => {
+        Ok(ImportMapTarget::Url(format!("{}{}", target, remainder)))
+      }
+      Err(err) => Err(err.clone()),
+    };
+
+    return Some((&entry.key, target));
+  }
+
+  None
+}
+
+/// Joins the components of a relative path with forward slashes, as in a URL.
+fn url_path(path: &Path) -> String {
+  path
+    .components()
+    .filter_map(|c| match c {
+      Component::Normal(c) => Some(c.to_string_lossy()),
+      _ => None,
+    })
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn resolve(
+    import_map: &ImportMap,
+    specifier: &str,
+    from: &str,
+  ) -> Option<Result<ImportMapTarget, ImportMapError>> {
+    let specifier = parse_specifier(specifier, Path::new(from)).unwrap();
+    import_map
+      .resolve(&specifier, Path::new(from))
+      .map(|(_, target)| target)
+  }
+
+  #[test]
+  fn test_import_map() {
+    let import_map = ImportMap::parse(
+      r#"{
+        "imports": {
+          "react": "./vendor/react.js",
+          "react/": "./vendor/react/",
+          "lodash/": "https://esm.sh/lodash/",
+          "./src/config.js": "/src/config.prod.js",
+          "invalid/": "./vendor/invalid.js",
+          "bare": "react",
+          "blocked": null
+        },
+        "scopes": {
+          "./legacy/": {
+            "react": "./vendor/react-legacy.js"
+          }
+        }
+      }"#,
+      Path::new("/app/importmap.json"),
+    )
+    .unwrap();
+
+    assert_eq!(
+      resolve(&import_map, "react", "/app/src/index.js"),
+      Some(Ok(ImportMapTarget::Path("/app/vendor/react.js".into())))
+    );
+    assert_eq!(
+      resolve(&import_map, "react/jsx-runtime", "/app/src/index.js"),
+      Some(Ok(ImportMapTarget::Path(
+        "/app/vendor/react/jsx-runtime".into()
+      )))
+    );
+    assert_eq!(
+      resolve(&import_map, "react/../../secret", "/app/src/index.js"),
+      Some(Err(ImportMapError::BacktrackingSpecifier))
+    );
+    assert_eq!(
+      resolve(&import_map, "lodash/fp/map.js", "/app/src/index.js"),
+      Some(Ok(ImportMapTarget::Url(
+        "https://esm.sh/lodash/fp/map.js".into()
+      )))
+    );
+    assert_eq!(
+      resolve(&import_map, "./config.js", "/app/src/index.js"),
+      Some(Ok(ImportMapTarget::Path("/app/src/config.prod.js".into())))
+    );
+    assert_eq!(
+      resolve(&import_map, "react", "/app/legacy/index.js"),
+      Some(Ok(ImportMapTarget::Path(
+        "/app/vendor/react-legacy.js".into()
+      )))
+    );
+    assert_eq!(
+      resolve(&import_map, "react/jsx-runtime", "/app/legacy/index.js"),
+      Some(Ok(ImportMapTarget::Path(
+        "/app/vendor/react/jsx-runtime".into()
+      )))
+    );
+    assert_eq!(
+      resolve(&import_map, "invalid/foo", "/app/src/index.js"),
+      Some(Err(ImportMapError::InvalidTarget))
+    );
+    assert_eq!(
+      resolve(&import_map, "bare", "/app/src/index.js"),
+      Some(Err(ImportMapError::InvalidTarget))
+    );
+    assert_eq!(
+      resolve(&import_map, "blocked", "/app/src/index.js"),
+      Some(Err(ImportMapError::NullEntry))
+    );
+    assert_eq!(resolve(&import_map, "vue", "/app/src/index.js"), None);
+  }
+}
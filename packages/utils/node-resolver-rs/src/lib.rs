@@ -1,41 +1,64 @@
 use std::{
   borrow::Cow,
+  cell::RefCell,
   collections::HashMap,
-  path::{Path, PathBuf},
+  ffi::OsString,
+  path::{Component, Path, PathBuf},
   sync::Arc,
 };
 
 use bitflags::bitflags;
 use once_cell::unsync::OnceCell;
 
-pub use cache::{Cache, CacheCow};
+pub use cache::{Cache, CacheCow, FileSystemEvent};
+pub use entry_points::ExportsEntry;
 pub use error::ResolverError;
+pub use exports_validation::{validate_exports, ExportsDiagnostic, ExportsDiagnosticKind};
 #[cfg(not(target_arch = "wasm32"))]
 pub use fs::OsFileSystem;
-pub use fs::{FileSystem, FileSystemRealPathCache};
+pub use fs::{FileMetadata, FileSystem, FileSystemRealPathCache};
+pub use import_map::{ImportMap, ImportMapError};
+use import_map::{ImportMapSpecifier, ImportMapTarget};
 pub use invalidations::*;
 use package_json::{AliasValue, ExportsResolution, PackageJson};
-pub use package_json::{ExportsCondition, Fields, ModuleType, PackageJsonError};
+pub use package_json::{ExportsCondition, Fields, ModuleType, PackageJsonError, SideEffectsReason};
+#[cfg(not(target_arch = "wasm32"))]
+pub use pnp::PnpFileSystem;
+pub use pnp::{PnpManifest, PnpResolution};
 pub use specifier::{
   parse_package_specifier, parse_scheme, Specifier, SpecifierError, SpecifierType,
 };
+pub use trace::TraceEvent;
 use tsconfig::TsConfig;
+pub use types_versions::TypeScriptVersion;
+pub use workspace::Workspace;
 
 use crate::path::resolve_path;
 
 mod builtins;
 mod cache;
+mod entry_points;
 mod error;
+mod exports_validation;
 mod fs;
+mod import_map;
 mod invalidations;
 mod package_json;
 mod path;
+mod pnp;
+mod snapshot;
 mod specifier;
+mod suggestions;
+mod trace;
 mod tsconfig;
+mod types_versions;
 mod url_to_path;
+mod workspace;
+#[cfg(not(target_arch = "wasm32"))]
+mod zip;
 
 bitflags! {
-  pub struct Flags: u16 {
+  pub struct Flags: u32 {
     /// Parcel-style absolute paths resolved relative to project root.
     const ABSOLUTE_SPECIFIERS = 1 << 0;
     /// Parcel-style tilde specifiers resolved relative to nearest module root.
@@ -59,6 +82,33 @@ bitflags! {
     const PARENT_EXTENSION = 1 << 9;
     /// Whether to allow optional extensions in the "exports" field.
     const EXPORTS_OPTIONAL_EXTENSIONS = 1 << 10;
+    /// Yarn Plug'n'Play manifests (.pnp.cjs), which replace node_modules lookups.
+    const PNP = 1 << 11;
+    /// Whether to verify that the casing of resolved paths matches the file system, so that
+    /// resolutions on case-insensitive file systems also work on case-sensitive ones.
+    /// This requires a `FileSystem` that supports `read_dir`.
+    const CASE_SENSITIVE = 1 << 12;
+    /// Whether to resolve TypeScript declarations instead of modules, following TSC.
+    /// Specifiers only resolve to TypeScript files, e.g. `./foo.js` -> `./foo.d.ts`,
+    /// and packages without types fall back to node_modules/@types.
+    const TYPESCRIPT_DECLARATIONS = 1 << 13;
+    /// Whether to resolve packages in the same monorepo workspace to their own directories
+    /// rather than via node_modules, preferring the "source" field if enabled.
+    /// Workspaces are declared by pnpm-workspace.yaml or the "workspaces" field in package.json.
+    /// Packages that do not satisfy the version range declared by the dependent use node_modules.
+    const WORKSPACES = 1 << 14;
+    /// Whether to keep symlinks in resolved paths, like Node's `--preserve-symlinks`, rather than
+    /// resolving them to their real paths. Dependencies of a symlinked package are then looked up
+    /// in the node_modules directories above the symlink, rather than above the real path.
+    const PRESERVE_SYMLINKS = 1 << 15;
+    /// Whether a nested package.json that does not start a new package and has no "sideEffects"
+    /// field, e.g. one that only sets "type", inherits the "sideEffects" field of the package above it.
+    /// Otherwise, files below it are assumed to have side effects, as in Node and webpack.
+    const INHERIT_SIDE_EFFECTS = 1 << 16;
+    /// Whether a nested package.json that does not start a new package, e.g. one that only sets
+    /// "type" or "sideEffects", inherits the "alias" and "browser" fields of the package above it,
+    /// as in browser-resolve. Otherwise, only the nearest package.json's fields apply.
+    const INHERIT_ALIASES = 1 << 17;
 
     /// Default Node settings for CommonJS.
     const NODE_CJS = Self::EXPORTS.bits | Self::DIR_INDEX.bits | Self::OPTIONAL_EXTENSIONS.bits;
@@ -84,6 +134,24 @@ impl Default for IncludeNodeModules {
 
 type ResolveModuleDir = dyn Fn(&str, &Path) -> Result<PathBuf, ResolverError> + Send + Sync;
 
+/// Resolves a specifier with a custom scheme, given the rest of the specifier after the
+/// scheme (e.g. "@std/path" for "jsr:@std/path") and the file it was imported from.
+/// Files that the result depends on should be added to the invalidations of the request.
+pub type ResolveScheme =
+  dyn Fn(&str, &Path, &Invalidations) -> Result<SchemeResolution, ResolverError> + Send + Sync;
+
+/// The result of resolving a specifier with a custom scheme.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SchemeResolution {
+  /// Another specifier to resolve instead, as if it was imported from the same file,
+  /// e.g. a package name or relative path. This may not use a custom scheme itself.
+  Specifier(String),
+  /// A file or directory.
+  Path(PathBuf),
+  /// The final resolution, e.g. a builtin or an external module.
+  Resolution(Resolution),
+}
+
 pub struct Resolver<'a> {
   pub project_root: Cow<'a, Path>,
   pub extensions: Extensions<'a>,
@@ -93,6 +161,17 @@ pub struct Resolver<'a> {
   pub include_node_modules: Cow<'a, IncludeNodeModules>,
   pub conditions: ExportsCondition,
   pub module_dir_resolver: Option<Arc<ResolveModuleDir>>,
+  /// Global folders to search after node_modules directories, e.g. from NODE_PATH.
+  pub module_paths: Vec<PathBuf>,
+  /// The path of an import map, which is applied before aliases and tsconfig paths.
+  /// It is read through the cache, so it is read again once it is invalidated.
+  pub import_map: Option<PathBuf>,
+  /// The TypeScript version used to select paths from the "typesVersions" field of package.json,
+  /// when resolving types. Defaults to the newest version.
+  pub typescript_version: Option<TypeScriptVersion>,
+  /// Resolvers for custom schemes, keyed by the scheme without the colon, e.g. "jsr" or "workspace".
+  /// These take precedence over the builtin handling of schemes such as `npm:` and `node:`.
+  pub schemes: HashMap<String, Arc<ResolveScheme>>,
   pub cache: CacheCow<'a>,
 }
 
@@ -114,6 +193,8 @@ impl<'a> Extensions<'a> {
 pub struct ResolveOptions {
   pub conditions: ExportsCondition,
   pub custom_conditions: Vec<String>,
+  /// Whether to record the steps taken during resolution in `ResolveResult::trace`.
+  pub trace: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
@@ -134,6 +215,53 @@ pub enum Resolution {
 pub struct ResolveResult {
   pub result: Result<(Resolution, Option<String>), ResolverError>,
   pub invalidations: Invalidations,
+  /// The steps taken during resolution, if tracing was enabled.
+  pub trace: Vec<TraceEvent>,
+}
+
+/// Whether a file has side effects, and the package.json that decided it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SideEffectsResolution {
+  pub side_effects: bool,
+  pub reason: SideEffectsReason,
+  /// The package.json whose "sideEffects" field was used, if any.
+  pub package_path: Option<PathBuf>,
+  /// Whether the package.json is an ancestor of the nearest one, which does not start a new package.
+  pub inherited: bool,
+}
+
+fn global_paths(
+  node_path: Option<OsString>,
+  home_dir: Option<PathBuf>,
+  node_exec_path: Option<PathBuf>,
+) -> Vec<PathBuf> {
+  let mut paths: Vec<PathBuf> = node_path
+    .as_deref()
+    .map(|node_path| {
+      std::env::split_paths(node_path)
+        .filter(|path| !path.as_os_str().is_empty())
+        .collect()
+    })
+    .unwrap_or_default();
+
+  if let Some(home_dir) = home_dir {
+    paths.push(home_dir.join(".node_modules"));
+    paths.push(home_dir.join(".node_libraries"));
+  }
+
+  // The prefix is the directory above the one containing node, except on Windows.
+  if let Some(node_exec_path) = node_exec_path {
+    let mut prefix = node_exec_path.parent();
+    if !cfg!(windows) {
+      prefix = prefix.and_then(|p| p.parent());
+    }
+    if let Some(prefix) = prefix {
+      paths.push(prefix.join("lib").join("node"));
+    }
+  }
+
+  paths
 }
 
 impl<'a> Resolver<'a> {
@@ -148,6 +276,10 @@ impl<'a> Resolver<'a> {
       include_node_modules: Cow::Owned(IncludeNodeModules::default()),
       conditions: ExportsCondition::NODE,
       module_dir_resolver: None,
+      module_paths: Self::node_global_paths(),
+      import_map: None,
+      typescript_version: None,
+      schemes: HashMap::new(),
     }
   }
 
@@ -162,6 +294,11 @@ impl<'a> Resolver<'a> {
       include_node_modules: Cow::Owned(IncludeNodeModules::default()),
       conditions: ExportsCondition::NODE,
       module_dir_resolver: None,
+      // NODE_PATH is not supported by ESM.
+      module_paths: Vec::new(),
+      import_map: None,
+      typescript_version: None,
+      schemes: HashMap::new(),
     }
   }
 
@@ -171,14 +308,61 @@ impl<'a> Resolver<'a> {
       extensions: Extensions::Borrowed(&["mjs", "js", "jsx", "cjs", "json"]),
       index_file: "index",
       entries: Fields::MAIN | Fields::SOURCE | Fields::BROWSER | Fields::MODULE,
-      flags: Flags::all(),
+      flags: Flags::all()
+        - Flags::PNP
+        - Flags::CASE_SENSITIVE
+        - Flags::TYPESCRIPT_DECLARATIONS
+        - Flags::WORKSPACES
+        - Flags::PRESERVE_SYMLINKS
+        - Flags::INHERIT_SIDE_EFFECTS
+        - Flags::INHERIT_ALIASES,
       cache,
       include_node_modules: Cow::Owned(IncludeNodeModules::default()),
       conditions: ExportsCondition::empty(),
       module_dir_resolver: None,
+      module_paths: Vec::new(),
+      import_map: None,
+      typescript_version: None,
+      schemes: HashMap::new(),
+    }
+  }
+
+  /// A resolver for TypeScript declarations, e.g. for bundling types or extracting APIs.
+  /// This resolves specifiers to .ts, .tsx and .d.ts files, packages via the "types" and
+  /// "typings" fields and the "types" export condition, and falls back to @types packages.
+  pub fn typescript_declarations(project_root: Cow<'a, Path>, cache: CacheCow<'a>) -> Self {
+    Self {
+      project_root,
+      extensions: Extensions::Borrowed(&["ts", "tsx", "d.ts"]),
+      index_file: "index",
+      entries: Fields::TYPES | Fields::MAIN,
+      flags: Flags::TYPESCRIPT | Flags::TYPESCRIPT_DECLARATIONS,
+      cache,
+      include_node_modules: Cow::Owned(IncludeNodeModules::default()),
+      conditions: ExportsCondition::TYPES,
+      module_dir_resolver: None,
+      module_paths: Vec::new(),
+      import_map: None,
+      typescript_version: None,
+      schemes: HashMap::new(),
     }
   }
 
+  /// Returns the global folders that Node searches for CommonJS modules:
+  /// NODE_PATH, $HOME/.node_modules, $HOME/.node_libraries and $PREFIX/lib/node.
+  /// The prefix is only known when running inside the node executable.
+  pub fn node_global_paths() -> Vec<PathBuf> {
+    let home_dir = std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" });
+    let node_exec_path = std::env::current_exe()
+      .ok()
+      .filter(|exe| exe.file_stem().is_some_and(|stem| stem == "node"));
+    global_paths(
+      std::env::var_os("NODE_PATH"),
+      home_dir.map(PathBuf::from),
+      node_exec_path,
+    )
+  }
+
   pub fn resolve(
     &self,
     specifier: &str,
@@ -196,12 +380,20 @@ impl<'a> Resolver<'a> {
     options: ResolveOptions,
   ) -> ResolveResult {
     let invalidations = Invalidations::default();
-    let result =
-      self.resolve_with_invalidations(specifier, from, specifier_type, &invalidations, options);
+    let trace = options.trace.then(RefCell::default);
+    let result = self.resolve_with_trace(
+      specifier,
+      from,
+      specifier_type,
+      &invalidations,
+      options,
+      trace.as_ref(),
+    );
 
     ResolveResult {
       result,
       invalidations,
+      trace: trace.map(RefCell::into_inner).unwrap_or_default(),
     }
   }
 
@@ -213,11 +405,37 @@ impl<'a> Resolver<'a> {
     invalidations: &Invalidations,
     options: ResolveOptions,
   ) -> Result<(Resolution, Option<String>), ResolverError> {
-    let (specifier, query) = match Specifier::parse(specifier, specifier_type, self.flags) {
-      Ok(s) => s,
-      Err(e) => return Err(e.into()),
+    self.resolve_with_trace(
+      specifier,
+      from,
+      specifier_type,
+      invalidations,
+      options,
+      None,
+    )
+  }
+
+  fn resolve_with_trace(
+    &self,
+    specifier: &str,
+    from: &Path,
+    specifier_type: SpecifierType,
+    invalidations: &Invalidations,
+    options: ResolveOptions,
+    trace: Option<&RefCell<Vec<TraceEvent>>>,
+  ) -> Result<(Resolution, Option<String>), ResolverError> {
+    let (specifier, query) = match parse_scheme(specifier) {
+      // Custom schemes are resolved in ResolveRequest::resolve_scheme, even in CommonJS.
+      Ok((scheme, _)) if self.schemes.contains_key(scheme.as_ref()) => {
+        (Specifier::Url(specifier), None)
+      }
+      _ => match Specifier::parse(specifier, specifier_type, self.flags) {
+        Ok(s) => s,
+        Err(e) => return Err(e.into()),
+      },
     };
     let mut request = ResolveRequest::new(self, &specifier, specifier_type, from, invalidations);
+    request.trace = trace;
     if !options.conditions.is_empty() || !options.custom_conditions.is_empty() {
       // If custom conditions are defined, these override the default conditions inferred from the specifier type.
       request.conditions = self.conditions | options.conditions;
@@ -225,20 +443,104 @@ impl<'a> Resolver<'a> {
     }
 
     match request.resolve() {
-      Ok(r) => Ok((r, query.map(|q| q.to_owned()))),
-      Err(r) => Err(r),
+      Ok(r) => {
+        if let Resolution::Path(path) = &r {
+          if self.flags.contains(Flags::CASE_SENSITIVE) {
+            self.check_case(path, invalidations)?;
+          }
+        }
+
+        Ok((r, query.map(|q| q.to_owned())))
+      }
+      Err(mut r) => {
+        request.add_suggestions(&mut r);
+        Err(r)
+      }
+    }
+  }
+
+  /// Checks that each component of a resolved path has the same casing as the entry on disk.
+  fn check_case(&self, path: &Path, invalidations: &Invalidations) -> Result<(), ResolverError> {
+    let mut actual_path = PathBuf::new();
+    let mut mismatch = false;
+    for component in path.components() {
+      if let Component::Normal(name) = component {
+        let name = name.to_string_lossy();
+        if let Some(entries) = self.cache.read_dir(&actual_path) {
+          if !entries.iter().any(|entry| *entry == name) {
+            let lower = name.to_lowercase();
+            if let Some(entry) = entries.iter().find(|entry| entry.to_lowercase() == lower) {
+              actual_path.push(entry);
+              mismatch = true;
+              continue;
+            }
+          }
+        }
+      }
+
+      actual_path.push(component);
+    }
+
+    if mismatch {
+      // Re-resolve if a file with the requested casing is created, e.g. when renaming the file.
+      invalidations.invalidate_on_file_create(path);
+      return Err(ResolverError::IncorrectCase {
+        path: path.to_owned(),
+        actual_path,
+      });
     }
+
+    Ok(())
   }
 
+  /// Returns whether a file has side effects, according to the "sideEffects" field of its package.json,
+  /// and why. With `Flags::INHERIT_SIDE_EFFECTS`, the result for a nested package.json without a
+  /// "sideEffects" field that does not start a new package is inherited from the package.json above it.
   pub fn resolve_side_effects(
     &self,
     path: &Path,
     invalidations: &Invalidations,
-  ) -> Result<bool, ResolverError> {
-    if let Some(package) = self.find_package(path.parent().unwrap(), invalidations)? {
-      Ok(package.has_side_effects(path))
-    } else {
-      Ok(true)
+  ) -> Result<SideEffectsResolution, ResolverError> {
+    let nearest = match self.find_package(path.parent().unwrap(), invalidations)? {
+      Some(package) => package,
+      None => {
+        return Ok(SideEffectsResolution {
+          side_effects: true,
+          reason: SideEffectsReason::NoPackage,
+          package_path: None,
+          inherited: false,
+        })
+      }
+    };
+
+    let mut package = nearest;
+    loop {
+      let (side_effects, reason) = package.explain_side_effects(path);
+      let package_dir = package.path.parent().unwrap();
+      let ancestor = if self.flags.contains(Flags::INHERIT_SIDE_EFFECTS)
+        && reason == SideEffectsReason::FieldAbsent
+        && !package.starts_package()
+        && package_dir != self.project_root
+      {
+        match package_dir.parent() {
+          Some(parent) => self.find_package(parent, invalidations)?,
+          None => None,
+        }
+      } else {
+        None
+      };
+
+      match ancestor {
+        Some(ancestor) => package = ancestor,
+        None => {
+          return Ok(SideEffectsResolution {
+            side_effects,
+            reason,
+            package_path: Some(package.path.clone()),
+            inherited: !std::ptr::eq(package, nearest),
+          })
+        }
+      }
     }
   }
 
@@ -283,6 +585,52 @@ impl<'a> Resolver<'a> {
     Ok(None)
   }
 
+  /// Finds the workspace containing a directory, declared by the nearest pnpm-workspace.yaml
+  /// file or package.json with a "workspaces" field above it.
+  pub fn find_workspace(
+    &self,
+    from: &Path,
+    invalidations: &Invalidations,
+  ) -> Result<Option<&Workspace>, ResolverError> {
+    for dir in from.ancestors() {
+      if dir.file_name().is_some_and(|name| name == "node_modules") {
+        break;
+      }
+
+      let manifest = dir.join("pnpm-workspace.yaml");
+      if self.cache.is_file(&manifest) {
+        invalidations.invalidate_on_file_change(&manifest);
+        return self.cache.read_workspace(&manifest).map(Some);
+      }
+
+      let manifest = dir.join("package.json");
+      if self.cache.is_file(&manifest) {
+        invalidations.invalidate_on_file_change(&manifest);
+        let package = self.cache.read_package(Cow::Borrowed(&manifest))?;
+        if !package.workspaces().is_empty() {
+          return self.cache.read_workspace(&manifest).map(Some);
+        }
+      }
+    }
+
+    invalidations.invalidate_on_file_create_above("pnpm-workspace.yaml", from);
+    invalidations.invalidate_on_file_create_above("package.json", from);
+    Ok(None)
+  }
+
+  /// Returns every entry point declared by the "exports" field of a package.json file, for each
+  /// subpath and combination of conditions. Subpath patterns are expanded to the files they match.
+  pub fn exports_entries(&self, package_path: &Path) -> Result<Vec<ExportsEntry>, ResolverError> {
+    let package = self.cache.read_package(Cow::Borrowed(package_path))?;
+    entry_points::exports_entries(&self.cache, package).map_err(|error| {
+      ResolverError::PackageJsonError {
+        module: package.name.to_owned(),
+        path: package.path.clone(),
+        error,
+      }
+    })
+  }
+
   fn find_ancestor_file(
     &self,
     from: &Path,
@@ -330,6 +678,7 @@ struct ResolveRequest<'a> {
   conditions: ExportsCondition,
   custom_conditions: &'a [String],
   priority_extension: Option<&'a str>,
+  trace: Option<&'a RefCell<Vec<TraceEvent>>>,
 }
 
 bitflags! {
@@ -337,6 +686,9 @@ bitflags! {
     const IN_TS_FILE = 1 << 0;
     const IN_JS_FILE = 1 << 1;
     const IN_NODE_MODULES = 1 << 2;
+    /// The specifier was returned by a custom scheme, so custom schemes are not resolved
+    /// again, which could otherwise recurse forever.
+    const FROM_SCHEME = 1 << 3;
   }
 }
 
@@ -399,6 +751,13 @@ impl<'a> ResolveRequest<'a> {
       conditions,
       custom_conditions: &[],
       priority_extension,
+      trace: None,
+    }
+  }
+
+  fn trace<F: FnOnce() -> TraceEvent>(&self, event: F) {
+    if let Some(trace) = self.trace {
+      trace.borrow_mut().push(event());
     }
   }
 
@@ -414,29 +773,59 @@ impl<'a> ResolveRequest<'a> {
     }
 
     match package.resolve_aliases(specifier, fields) {
-      Some(alias) => match alias.as_ref() {
-        AliasValue::Specifier(specifier) => {
-          let mut req = ResolveRequest::new(
-            self.resolver,
-            specifier,
-            SpecifierType::Cjs,
-            &package.path,
-            self.invalidations,
-          );
-          req.priority_extension = self.priority_extension;
-          req.conditions = self.conditions;
-          req.custom_conditions = self.custom_conditions;
-          let resolved = req.resolve()?;
-          Ok(Some(resolved))
-        }
-        AliasValue::Bool(false) => Ok(Some(Resolution::Empty)),
-        AliasValue::Bool(true) => Ok(None),
-        AliasValue::Global { global } => Ok(Some(Resolution::Global((*global).to_owned()))),
-      },
+      Some(alias) => {
+        self.trace(|| TraceEvent::Alias {
+          package_path: package.path.clone(),
+          field: if fields.contains(Fields::BROWSER)
+            && package
+              .resolve_aliases(specifier, fields - Fields::BROWSER)
+              .is_none()
+          {
+            "browser"
+          } else {
+            "alias"
+          },
+          specifier: specifier.to_string().into_owned(),
+          value: match alias.as_ref() {
+            AliasValue::Specifier(specifier) => specifier.to_string().into_owned(),
+            AliasValue::Bool(b) => b.to_string(),
+            AliasValue::Global { global } => format!("global:{}", global),
+          },
+        });
+        self.resolve_alias_value(package, &alias)
+      }
       None => Ok(None),
     }
   }
 
+  fn resolve_alias_value(
+    &self,
+    package: &PackageJson,
+    alias: &AliasValue,
+  ) -> Result<Option<Resolution>, ResolverError> {
+    match alias {
+      AliasValue::Specifier(specifier) => {
+        let mut req = ResolveRequest::new(
+          self.resolver,
+          specifier,
+          SpecifierType::Cjs,
+          &package.path,
+          self.invalidations,
+        );
+        req.flags |= self.flags & RequestFlags::FROM_SCHEME;
+        req.priority_extension = self.priority_extension;
+        req.conditions = self.conditions;
+        req.custom_conditions = self.custom_conditions;
+        req.trace = self.trace;
+        let resolved = req.resolve()?;
+        Ok(Some(resolved))
+      }
+      AliasValue::Bool(false) => Ok(Some(Resolution::Empty)),
+      AliasValue::Bool(true) => Ok(None),
+      AliasValue::Global { global } => Ok(Some(Resolution::Global((*global).to_owned()))),
+    }
+  }
+
   fn root_package(&self) -> Result<&Option<&PackageJson>, ResolverError> {
     self
       .root_package
@@ -444,6 +833,23 @@ impl<'a> ResolveRequest<'a> {
   }
 
   fn resolve(&self) -> Result<Resolution, ResolverError> {
+    self.trace(|| TraceEvent::Request {
+      specifier: self.specifier.to_string().into_owned(),
+      from: self.from.to_owned(),
+    });
+
+    // Import maps apply to JS imports before anything else, as in browsers.
+    if let Some(import_map) = &self.resolver.import_map {
+      if self.specifier_type != SpecifierType::Url {
+        // Any change to the import map, e.g. adding an entry, may change the result.
+        self.invalidations.invalidate_on_file_change(import_map);
+        let import_map = self.resolver.cache.read_import_map(import_map)?;
+        if let Some(res) = self.resolve_import_map(import_map)? {
+          return Ok(res);
+        }
+      }
+    }
+
     match &self.specifier {
       Specifier::Relative(specifier) => {
         // Relative path
@@ -473,6 +879,7 @@ impl<'a> ResolveRequest<'a> {
           Err(ResolverError::FileNotFound {
             relative: specifier.as_ref().to_owned(),
             from: PathBuf::from("/"),
+            suggestions: Vec::new(),
           })
         }
       }
@@ -486,13 +893,25 @@ impl<'a> ResolveRequest<'a> {
           // An internal package #import specifier.
           let package = self.find_package(self.from.parent().unwrap_or_else(|| self.from))?;
           if let Some(package) = package {
+            let (conditions, custom_conditions) = self.exports_conditions()?;
             let res = package
-              .resolve_package_imports(hash, self.conditions, self.custom_conditions)
+              .resolve_package_imports(hash, conditions, &custom_conditions)
               .map_err(|error| ResolverError::PackageJsonError {
                 error,
                 module: package.name.to_owned(),
                 path: package.path.clone(),
               })?;
+            self.trace(|| TraceEvent::PackageExports {
+              package_path: package.path.clone(),
+              field: "imports",
+              subpath: hash.to_string(),
+              conditions: trace::condition_names(conditions, &custom_conditions),
+              target: match &res {
+                ExportsResolution::Path(path) => path.to_string_lossy().into_owned(),
+                ExportsResolution::Package(specifier) => specifier.to_string(),
+                ExportsResolution::None => String::new(),
+              },
+            });
             match res {
               ExportsResolution::Path(path) => {
                 // Extensionless specifiers are not supported in the imports field.
@@ -527,6 +946,10 @@ impl<'a> ResolveRequest<'a> {
         Ok(Resolution::Builtin(builtin.as_ref().to_owned()))
       }
       Specifier::Url(url) => {
+        if let Some(res) = self.resolve_scheme(url)? {
+          return Ok(res);
+        }
+
         if self.specifier_type == SpecifierType::Url {
           Ok(Resolution::External)
         } else {
@@ -540,6 +963,124 @@ impl<'a> ResolveRequest<'a> {
     }
   }
 
+  fn resolve_scheme(&self, url: &str) -> Result<Option<Resolution>, ResolverError> {
+    let (scheme, rest) = match parse_scheme(url) {
+      Ok(res) => res,
+      Err(_) => return Ok(None),
+    };
+    let resolve_scheme = match self.resolver.schemes.get(scheme.as_ref()) {
+      Some(resolve_scheme) => resolve_scheme,
+      None => return Ok(None),
+    };
+
+    if self.flags.contains(RequestFlags::FROM_SCHEME) {
+      return Ok(None);
+    }
+
+    let resolution = resolve_scheme(rest, self.from, self.invalidations)?;
+    self.trace(|| TraceEvent::Scheme {
+      scheme: scheme.to_string(),
+      specifier: url.to_owned(),
+      target: match &resolution {
+        SchemeResolution::Specifier(specifier) => specifier.clone(),
+        SchemeResolution::Path(path) => path.to_string_lossy().into_owned(),
+        SchemeResolution::Resolution(res) => format!("{:?}", res),
+      },
+    });
+
+    match resolution {
+      SchemeResolution::Specifier(specifier) => {
+        let (specifier, _) =
+          Specifier::parse(&specifier, self.specifier_type, self.resolver.flags)?;
+        let mut req = ResolveRequest::new(
+          self.resolver,
+          &specifier,
+          self.specifier_type,
+          self.from,
+          self.invalidations,
+        );
+        req.flags |= RequestFlags::FROM_SCHEME;
+        req.priority_extension = self.priority_extension;
+        req.conditions = self.conditions;
+        req.custom_conditions = self.custom_conditions;
+        req.trace = self.trace;
+        Ok(Some(req.resolve()?))
+      }
+      SchemeResolution::Path(path) => match self.load_path(&path, None)? {
+        Some(res) => Ok(Some(res)),
+        None => Err(ResolverError::FileNotFound {
+          relative: path,
+          from: self.from.to_owned(),
+          suggestions: Vec::new(),
+        }),
+      },
+      SchemeResolution::Resolution(res) => Ok(Some(res)),
+    }
+  }
+
+  fn resolve_import_map(
+    &self,
+    import_map: &ImportMap,
+  ) -> Result<Option<Resolution>, ResolverError> {
+    let specifier = match &self.specifier {
+      Specifier::Relative(specifier) => {
+        ImportMapSpecifier::Path(resolve_path(self.from, specifier))
+      }
+      Specifier::Absolute(specifier) => {
+        if self.resolver.flags.contains(Flags::ABSOLUTE_SPECIFIERS) {
+          ImportMapSpecifier::Path(
+            self
+              .resolver
+              .project_root
+              .join(specifier.strip_prefix("/").unwrap()),
+          )
+        } else {
+          ImportMapSpecifier::Path(specifier.as_ref().to_owned())
+        }
+      }
+      Specifier::Package(..) | Specifier::Builtin(..) | Specifier::Url(..) => {
+        ImportMapSpecifier::Specifier(self.specifier.to_string().into_owned())
+      }
+      Specifier::Tilde(..) | Specifier::Hash(..) => return Ok(None),
+    };
+
+    let (key, target) = match import_map.resolve(&specifier, self.from) {
+      Some((key, Ok(target))) => (key, target),
+      Some((key, Err(error))) => {
+        return Err(ResolverError::ImportMapError {
+          import_map: import_map.path.clone(),
+          key: key.to_owned(),
+          error,
+        })
+      }
+      None => return Ok(None),
+    };
+
+    self.trace(|| TraceEvent::ImportMap {
+      import_map: import_map.path.clone(),
+      key: key.to_owned(),
+      target: match &target {
+        ImportMapTarget::Path(path) => path.to_string_lossy().into_owned(),
+        ImportMapTarget::Url(url) => url.clone(),
+      },
+    });
+
+    match target {
+      ImportMapTarget::Url(_) => Ok(Some(Resolution::External)),
+      ImportMapTarget::Path(path) => {
+        if let Some(res) = self.load_path(&path, None)? {
+          return Ok(Some(res));
+        }
+
+        Err(ResolverError::FileNotFound {
+          relative: path,
+          from: import_map.path.clone(),
+          suggestions: Vec::new(),
+        })
+      }
+    }
+  }
+
   fn find_ancestor_file(&self, from: &Path, filename: &str) -> Option<PathBuf> {
     let from = from.parent().unwrap();
     self
@@ -551,11 +1092,37 @@ impl<'a> ResolveRequest<'a> {
     self.resolver.find_package(from, self.invalidations)
   }
 
+  /// Finds the package.json whose aliases apply to files in a directory. This is the nearest one,
+  /// except that with `Flags::INHERIT_ALIASES`, nested package.json files that do not start a new
+  /// package are skipped.
+  fn find_alias_package(&self, dir: &Path) -> Result<Option<&'a PackageJson<'a>>, ResolverError> {
+    let mut package = self.find_package(dir)?;
+    while let Some(p) = package {
+      let package_dir = p.path.parent().unwrap();
+      if self.has_own_aliases(p) || package_dir == self.resolver.project_root {
+        break;
+      }
+
+      package = match package_dir.parent() {
+        Some(parent) => self.find_package(parent)?,
+        None => None,
+      };
+    }
+
+    Ok(package)
+  }
+
+  /// Whether a package.json's own aliases apply to the files below it, rather than those of the
+  /// package above it.
+  fn has_own_aliases(&self, package: &PackageJson) -> bool {
+    !self.resolver.flags.contains(Flags::INHERIT_ALIASES) || package.starts_package()
+  }
+
   fn resolve_relative(&self, specifier: &Path, from: &Path) -> Result<Resolution, ResolverError> {
     // Resolve aliases from the nearest package.json.
     let path = resolve_path(from, specifier);
     let package = if self.resolver.flags.contains(Flags::ALIASES) {
-      self.find_package(path.parent().unwrap())?
+      self.find_alias_package(path.parent().unwrap())?
     } else {
       None
     };
@@ -564,12 +1131,183 @@ impl<'a> ResolveRequest<'a> {
       return Ok(res);
     }
 
+    // Try the same relative path in each of the other tsconfig rootDirs.
+    if let Some(tsconfig) = self.tsconfig()? {
+      for path in tsconfig.root_dirs(&path) {
+        self.trace(|| TraceEvent::TsConfig {
+          tsconfig: tsconfig.path.clone(),
+          option: "rootDirs",
+          path: path.clone(),
+        });
+
+        let package = if self.resolver.flags.contains(Flags::ALIASES) {
+          self.find_alias_package(path.parent().unwrap())?
+        } else {
+          None
+        };
+
+        if let Some(res) = self.load_path(&path, package)? {
+          return Ok(res);
+        }
+      }
+    }
+
     Err(ResolverError::FileNotFound {
       relative: specifier.to_owned(),
       from: from.to_owned(),
+      suggestions: Vec::new(),
     })
   }
 
+  /// Fills in the suggestions of an error that is returned to the caller. They are computed here
+  /// rather than where the error is created, because errors from e.g. the @types fallback may be
+  /// recovered from, and listing directories is only worth it for errors that are reported.
+  fn add_suggestions(&self, error: &mut ResolverError) {
+    match error {
+      ResolverError::FileNotFound {
+        relative,
+        from,
+        suggestions,
+      } => {
+        let path = resolve_path(&*from, &*relative);
+        *suggestions = self
+          .similar_files(&path)
+          .into_iter()
+          .map(|name| relative.with_file_name(name))
+          .collect();
+      }
+      ResolverError::ModuleNotFound {
+        module,
+        suggestions,
+      } => {
+        *suggestions = match self.pnp_manifest() {
+          Some(manifest) if manifest.resolve(module, self.from) == PnpResolution::NotFound => {
+            manifest.similar_dependencies(module, self.from)
+          }
+          _ => self.similar_modules(module),
+        };
+      }
+      ResolverError::ModuleSubpathNotFound {
+        module,
+        path,
+        package_path,
+        suggestions,
+      } => {
+        *suggestions = self.similar_subpaths(module, path, package_path);
+      }
+      _ => {}
+    }
+  }
+
+  /// Returns the names of files next to the given path with a similar name,
+  /// e.g. with different case or a different extension.
+  fn similar_files(&self, path: &Path) -> Vec<String> {
+    let (dir, name) = match (path.parent(), path.file_name()) {
+      (Some(dir), Some(name)) => (dir, name.to_string_lossy()),
+      _ => return Vec::new(),
+    };
+
+    match self.resolver.cache.read_dir(dir) {
+      Some(entries) => suggestions::similar(
+        &name,
+        entries
+          .iter()
+          .filter(|entry| !entry.starts_with('.'))
+          .cloned(),
+      ),
+      None => Vec::new(),
+    }
+  }
+
+  /// Returns specifiers for subpaths of a package that are similar to the one that was not found.
+  /// These are the other subpaths the package exports, or similarly named files in the package.
+  fn similar_subpaths(&self, module: &str, path: &Path, package_path: &Path) -> Vec<String> {
+    let package = match self
+      .resolver
+      .cache
+      .read_package(Cow::Borrowed(package_path))
+    {
+      Ok(package) => package,
+      Err(_) => return Vec::new(),
+    };
+
+    // The requested subpath is only known if the package was imported by this request,
+    // rather than e.g. by an alias.
+    let subpath = match &self.specifier {
+      Specifier::Package(specifier_module, subpath) if specifier_module == module => subpath,
+      _ => return Vec::new(),
+    };
+
+    if self.resolver.flags.contains(Flags::EXPORTS) && package.has_exports() {
+      // Suggest the other subpaths the package exports, most similar first.
+      let mut subpaths = package.exports_subpaths();
+      // Skip the subpath itself, and patterns it already matched.
+      let requested = format!("./{}", subpath);
+      subpaths.retain(|s| match s.split_once('*') {
+        Some((prefix, suffix)) => !(requested.starts_with(prefix) && requested.ends_with(suffix)),
+        None => *s != requested,
+      });
+      suggestions::sort_by_similarity(&requested, &mut subpaths);
+
+      return subpaths
+        .into_iter()
+        .map(|s| match s.strip_prefix("./") {
+          Some(s) => format!("{}/{}", module, s),
+          None => module.to_owned(),
+        })
+        .collect();
+    }
+
+    // Only suggest files if the subpath itself was not found, not e.g. the package entry.
+    if subpath.is_empty() || path != package_path.with_file_name(subpath.as_ref()) {
+      return Vec::new();
+    }
+
+    self
+      .similar_files(path)
+      .into_iter()
+      .map(|name| {
+        let subpath = Path::new(subpath.as_ref()).with_file_name(name);
+        format!("{}/{}", module, subpath.to_string_lossy())
+      })
+      .collect()
+  }
+
+  /// Returns installed packages with a similar name, searching the same directories as resolve_node_module.
+  fn similar_modules(&self, module: &str) -> Vec<String> {
+    let node_modules = self
+      .from
+      .ancestors()
+      .filter(|dir| !dir.ends_with("node_modules"))
+      .map(|dir| dir.join("node_modules"))
+      .chain(self.resolver.module_paths.iter().cloned());
+
+    let mut names = Vec::new();
+    for dir in node_modules {
+      let entries = match self.resolver.cache.read_dir(&dir) {
+        Some(entries) => entries,
+        None => continue,
+      };
+
+      for entry in entries.iter() {
+        if entry.starts_with('.') {
+          continue;
+        }
+
+        // Scoped packages are nested one level deeper.
+        if entry.starts_with('@') {
+          if let Some(children) = self.resolver.cache.read_dir(&dir.join(entry)) {
+            names.extend(children.iter().map(|child| format!("{}/{}", entry, child)));
+          }
+        } else {
+          names.push(entry.clone());
+        }
+      }
+    }
+
+    suggestions::similar(module, names)
+  }
+
   fn resolve_bare(&self, module: &str, subpath: &str) -> Result<Resolution, ResolverError> {
     let include = match self.resolver.include_node_modules.as_ref() {
       IncludeNodeModules::Bool(b) => *b,
@@ -603,7 +1341,9 @@ impl<'a> ResolveRequest<'a> {
       }
 
       // Next, try the local package.json.
-      if let Some(package) = self.find_package(self.from.parent().unwrap_or_else(|| self.from))? {
+      if let Some(package) =
+        self.find_alias_package(self.from.parent().unwrap_or_else(|| self.from))?
+      {
         let mut fields = Fields::ALIAS;
         if self.resolver.entries.contains(Fields::BROWSER) {
           fields |= Fields::BROWSER;
@@ -619,16 +1359,46 @@ impl<'a> ResolveRequest<'a> {
   }
 
   fn resolve_node_module(&self, module: &str, subpath: &str) -> Result<Resolution, ResolverError> {
+    if self.resolver.flags.contains(Flags::WORKSPACES) {
+      if let Some(res) = self.resolve_workspace_package(module, subpath)? {
+        return Ok(res);
+      }
+    }
+
     // If there is a custom module directory resolver (e.g. Yarn PnP), use that.
     if let Some(module_dir_resolver) = &self.resolver.module_dir_resolver {
       let package_dir = module_dir_resolver(module, self.from)?;
+      self.trace_package_directory(module, &package_dir, true);
       return self.resolve_package(package_dir, module, subpath);
     } else {
+      if self.resolver.flags.contains(Flags::PNP) {
+        if let Some(package_dir) = self.resolve_pnp(module)? {
+          self.trace_package_directory(module, &package_dir, true);
+          return self.resolve_package(package_dir, module, subpath);
+        }
+      }
+
       self.invalidations.invalidate_on_file_create_above(
         format!("node_modules/{}", module),
         self.from.parent().unwrap_or_else(|| self.from),
       );
 
+      // Types for "@scope/pkg" are published as "@types/scope__pkg".
+      let declarations = self.resolver.flags.contains(Flags::TYPESCRIPT_DECLARATIONS);
+      let types_module = format!(
+        "@types/{}",
+        module
+          .strip_prefix('@')
+          .unwrap_or(module)
+          .replace('/', "__")
+      );
+      if declarations {
+        self.invalidations.invalidate_on_file_create_above(
+          format!("node_modules/{}", types_module),
+          self.from.parent().unwrap_or(self.from),
+        );
+      }
+
       for dir in self.from.ancestors() {
         // Skip over node_modules directories
         if let Some(filename) = dir.file_name() {
@@ -638,19 +1408,188 @@ impl<'a> ResolveRequest<'a> {
         }
 
         let package_dir = dir.join("node_modules").join(module);
-        if self.resolver.cache.is_dir(&package_dir) {
-          return self.resolve_package(package_dir, module, subpath);
+        let found = self.resolver.cache.is_dir(&package_dir);
+        self.trace_package_directory(module, &package_dir, found);
+        if !declarations {
+          if found {
+            return self.resolve_package(package_dir, module, subpath);
+          }
+          continue;
         }
-      }
-    }
 
-    // NODE_PATH??
+        // Like TSC, fall back to @types in the same node_modules directory
+        // if the package does not exist or does not include types.
+        let res = if found {
+          match self.resolve_package(package_dir, module, subpath) {
+            Ok(res) => return Ok(res),
+            Err(err) => Some(err),
+          }
+        } else {
+          None
+        };
+
+        let types_dir = dir.join("node_modules").join(&types_module);
+        let types_found = self.resolver.cache.is_dir(&types_dir);
+        self.trace_package_directory(&types_module, &types_dir, types_found);
+        if types_found {
+          return self.resolve_package(types_dir, &types_module, subpath);
+        }
+
+        if let Some(err) = res {
+          return Err(err);
+        }
+      }
+    }
+
+    // Finally, try the global folders, e.g. NODE_PATH. These are read from environment variables
+    // when the resolver is created, so the result may change when the process restarts.
+    if !self.resolver.module_paths.is_empty() {
+      self.invalidations.invalidate_on_startup();
+    }
+    for dir in &self.resolver.module_paths {
+      let package_dir = dir.join(module);
+      let found = self.resolver.cache.is_dir(&package_dir);
+      self.trace_package_directory(module, &package_dir, found);
+      if found {
+        return self.resolve_package(package_dir, module, subpath);
+      }
+
+      self.invalidations.invalidate_on_file_create(&package_dir);
+    }
 
     Err(ResolverError::ModuleNotFound {
       module: module.to_owned(),
+      suggestions: Vec::new(),
     })
   }
 
+  /// Resolves a package in the same workspace from its own directory, rather than via node_modules.
+  fn resolve_workspace_package(
+    &self,
+    module: &str,
+    subpath: &str,
+  ) -> Result<Option<Resolution>, ResolverError> {
+    let workspace = match self
+      .resolver
+      .find_workspace(self.from.parent().unwrap_or(self.from), self.invalidations)?
+    {
+      Some(workspace) => workspace,
+      None => return Ok(None),
+    };
+
+    // Re-resolve if a matching package is added to the workspace.
+    for pattern in &workspace.patterns {
+      if !pattern.starts_with('!') {
+        let glob = workspace.root().join(pattern).join("package.json");
+        self
+          .invalidations
+          .invalidate_on_glob_create(glob.to_string_lossy());
+      }
+    }
+
+    let package_dir = match workspace.package_dir(module) {
+      Some(package_dir) => package_dir.to_owned(),
+      None => return Ok(None),
+    };
+    let package_path = package_dir.join("package.json");
+    let package = self.invalidations.read(&package_path, || {
+      self
+        .resolver
+        .cache
+        .read_package(Cow::Borrowed(&package_path))
+    })?;
+
+    // If the dependent declares a range that the workspace package does not satisfy, e.g. to use an
+    // older published version, fall back to node_modules. Undeclared dependencies use the workspace.
+    if let Some(range) = self
+      .find_package(self.from.parent().unwrap_or(self.from))?
+      .and_then(|dependent| dependent.dependency_range(module))
+    {
+      if !package.satisfies(range) {
+        return Ok(None);
+      }
+    }
+
+    self.trace_package_directory(module, &package_dir, true);
+
+    // Packages in node_modules only use the "source" field when symlinked, but workspace
+    // packages are always resolved from their own directories.
+    if self.resolver.entries.contains(Fields::SOURCE) && subpath.is_empty() {
+      if let Some(source) = package.workspace_source() {
+        self.trace(|| TraceEvent::PackageEntry {
+          package_path: package.path.clone(),
+          field: "source",
+          path: source.clone(),
+        });
+        if let Some(res) = self.load_path(&source, Some(package))? {
+          return Ok(Some(res));
+        }
+      }
+    }
+
+    self.resolve_package(package_dir, module, subpath).map(Some)
+  }
+
+  fn trace_package_directory(&self, module: &str, path: &Path, found: bool) {
+    self.trace(|| TraceEvent::PackageDirectory {
+      module: module.to_owned(),
+      path: path.to_owned(),
+      found,
+    });
+  }
+
+  /// Finds the nearest .pnp.cjs file. Unlike package.json, this may be above node_modules
+  /// directories, e.g. when the issuer is inside a zip archive in the Yarn cache.
+  fn find_pnp_manifest(&self) -> Option<PathBuf> {
+    let from = self.from.parent().unwrap_or(self.from);
+    from
+      .ancestors()
+      .map(|dir| dir.join(".pnp.cjs"))
+      .find(|path| self.resolver.cache.is_file(path))
+  }
+
+  /// Returns the PnP manifest that applies to this request, if PnP is enabled.
+  fn pnp_manifest(&self) -> Option<&'a PnpManifest> {
+    if !self.resolver.flags.contains(Flags::PNP) || self.resolver.module_dir_resolver.is_some() {
+      return None;
+    }
+
+    let manifest_path = self.find_pnp_manifest()?;
+    self.resolver.cache.read_pnp_manifest(&manifest_path).ok()
+  }
+
+  fn resolve_pnp(&self, module: &str) -> Result<Option<PathBuf>, ResolverError> {
+    let manifest_path = match self.find_pnp_manifest() {
+      Some(path) => path,
+      None => {
+        self
+          .invalidations
+          .invalidate_on_file_create_above(".pnp.cjs", self.from.parent().unwrap_or(self.from));
+        return Ok(None);
+      }
+    };
+
+    let manifest = self.invalidations.read(&manifest_path, || {
+      self.resolver.cache.read_pnp_manifest(&manifest_path)
+    })?;
+
+    match &manifest.data_path {
+      Some(data_path) => self.invalidations.invalidate_on_file_change(data_path),
+      None => self
+        .invalidations
+        .invalidate_on_file_create(&manifest_path.with_file_name(".pnp.data.json")),
+    }
+
+    match manifest.resolve(module, self.from) {
+      PnpResolution::Package(package_dir) => Ok(Some(package_dir)),
+      PnpResolution::Fallback => Ok(None),
+      PnpResolution::NotFound => Err(ResolverError::ModuleNotFound {
+        module: module.to_owned(),
+        suggestions: Vec::new(),
+      }),
+    }
+  }
+
   fn resolve_package(
     &self,
     mut package_dir: PathBuf,
@@ -677,6 +1616,7 @@ impl<'a> ResolveRequest<'a> {
 
         return Err(ResolverError::ModuleNotFound {
           module: module.to_owned(),
+          suggestions: Vec::new(),
         });
       }
       Err(err) => return Err(err),
@@ -685,6 +1625,11 @@ impl<'a> ResolveRequest<'a> {
     // Try the "source" field first, if present.
     if self.resolver.entries.contains(Fields::SOURCE) && subpath.is_empty() {
       if let Some(source) = package.source() {
+        self.trace(|| TraceEvent::PackageEntry {
+          package_path: package.path.clone(),
+          field: "source",
+          path: source.clone(),
+        });
         if let Some(res) = self.load_path(&source, Some(package))? {
           return Ok(res);
         }
@@ -694,13 +1639,21 @@ impl<'a> ResolveRequest<'a> {
     // If the exports field is present, use the Node ESM algorithm.
     // Otherwise, fall back to classic CJS resolution.
     if self.resolver.flags.contains(Flags::EXPORTS) && package.has_exports() {
+      let (conditions, custom_conditions) = self.exports_conditions()?;
       let path = package
-        .resolve_package_exports(subpath, self.conditions, self.custom_conditions)
+        .resolve_package_exports(subpath, conditions, &custom_conditions)
         .map_err(|e| ResolverError::PackageJsonError {
           module: package.name.to_owned(),
           path: package.path.clone(),
           error: e,
         })?;
+      self.trace(|| TraceEvent::PackageExports {
+        package_path: package.path.clone(),
+        field: "exports",
+        subpath: subpath.to_owned(),
+        conditions: trace::condition_names(conditions, &custom_conditions),
+        target: path.to_string_lossy().into_owned(),
+      });
 
       // Extensionless specifiers are not supported in the exports field
       // according to the Node spec (for both ESM and CJS). However, webpack
@@ -722,8 +1675,25 @@ impl<'a> ResolveRequest<'a> {
         module: module.to_owned(),
         path,
         package_path: package.path.clone(),
+        suggestions: Vec::new(),
       })
     } else if !subpath.is_empty() {
+      if let Some(paths) = self.types_versions(package, subpath) {
+        if let Some(res) = self.load_types_versions(package, &paths)? {
+          return Ok(res);
+        }
+
+        return Err(ResolverError::ModuleSubpathNotFound {
+          module: module.to_owned(),
+          path: paths
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| package_dir.join(subpath)),
+          package_path: package.path.clone(),
+          suggestions: Vec::new(),
+        });
+      }
+
       package_dir.push(subpath);
       if let Some(res) = self.load_path(&package_dir, Some(package))? {
         return Ok(res);
@@ -733,6 +1703,7 @@ impl<'a> ResolveRequest<'a> {
         module: module.to_owned(),
         path: package_dir,
         package_path: package.path.clone(),
+        suggestions: Vec::new(),
       })
     } else {
       let res = self.try_package_entries(package);
@@ -742,9 +1713,11 @@ impl<'a> ResolveRequest<'a> {
 
       // Node ESM doesn't allow directory imports.
       if self.resolver.flags.contains(Flags::DIR_INDEX) {
-        if let Some(res) =
-          self.load_file(&package_dir.join(self.resolver.index_file), Some(package))?
-        {
+        let res = match self.types_versions(package, self.resolver.index_file) {
+          Some(paths) => self.load_types_versions(package, &paths)?,
+          None => self.load_file(&package_dir.join(self.resolver.index_file), Some(package))?,
+        };
+        if let Some(res) = res {
           return Ok(res);
         }
       }
@@ -755,6 +1728,7 @@ impl<'a> ResolveRequest<'a> {
         module: module.to_owned(),
         path: package_dir.join(self.resolver.index_file),
         package_path: package.path.clone(),
+        suggestions: Vec::new(),
       })
     }
   }
@@ -765,7 +1739,23 @@ impl<'a> ResolveRequest<'a> {
   ) -> Result<Option<Resolution>, ResolverError> {
     // Try all entry fields.
     if let Some((entry, field)) = package.entries(self.resolver.entries).next() {
-      if let Some(res) = self.load_path(&entry, Some(package))? {
+      self.trace(|| TraceEvent::PackageEntry {
+        package_path: package.path.clone(),
+        field,
+        path: entry.clone(),
+      });
+
+      let res = match package
+        .path
+        .parent()
+        .and_then(|dir| entry.strip_prefix(dir).ok())
+        .and_then(|subpath| self.types_versions(package, &subpath.to_string_lossy()))
+      {
+        Some(paths) => self.load_types_versions(package, &paths)?,
+        None => self.load_path(&entry, Some(package))?,
+      };
+
+      if let Some(res) = res {
         return Ok(Some(res));
       } else {
         return Err(ResolverError::ModuleEntryNotFound {
@@ -780,6 +1770,35 @@ impl<'a> ResolveRequest<'a> {
     Ok(None)
   }
 
+  /// Maps a path within a package via its "typesVersions" field, when resolving types.
+  fn types_versions(&self, package: &PackageJson, subpath: &str) -> Option<Vec<PathBuf>> {
+    if !self.resolver.entries.contains(Fields::TYPES) {
+      return None;
+    }
+
+    package.resolve_types_versions(subpath, self.resolver.typescript_version)
+  }
+
+  /// Loads the first path mapped by the "typesVersions" field that exists.
+  fn load_types_versions(
+    &self,
+    package: &PackageJson,
+    paths: &[PathBuf],
+  ) -> Result<Option<Resolution>, ResolverError> {
+    for path in paths {
+      self.trace(|| TraceEvent::PackageEntry {
+        package_path: package.path.clone(),
+        field: "typesVersions",
+        path: path.clone(),
+      });
+      if let Some(res) = self.load_path(path, Some(package))? {
+        return Ok(Some(res));
+      }
+    }
+
+    Ok(None)
+  }
+
   fn load_path(
     &self,
     path: &Path,
@@ -816,6 +1835,10 @@ impl<'a> ResolveRequest<'a> {
     path: &Path,
     package: Option<&PackageJson>,
   ) -> Result<Option<Resolution>, ResolverError> {
+    if self.resolver.flags.contains(Flags::TYPESCRIPT_DECLARATIONS) {
+      return self.load_declaration_file(path, package);
+    }
+
     // First try the path as is.
     // TypeScript only supports resolving specifiers ending with `.ts` or `.tsx`
     // in a certain mode, but we always allow it.
@@ -907,6 +1930,36 @@ impl<'a> ResolveRequest<'a> {
     Ok(None)
   }
 
+  /// Loads a TypeScript file for the given path, as TSC does when resolving declarations.
+  /// Unlike in load_file, JavaScript files are never resolved.
+  fn load_declaration_file(
+    &self,
+    path: &Path,
+    package: Option<&PackageJson>,
+  ) -> Result<Option<Resolution>, ResolverError> {
+    let ext = path.extension().and_then(|ext| ext.to_str());
+    if matches!(ext, Some("ts" | "tsx" | "mts" | "cts")) {
+      return self.try_suffixes(path, "", package, false);
+    }
+
+    // JavaScript extensions are replaced, e.g. "./foo.js" -> "./foo.d.ts".
+    // Otherwise, TypeScript extensions are appended.
+    let (path, extensions): (Cow<'_, Path>, &[&str]) = match ext {
+      Some("js" | "jsx") => (Cow::Owned(path.with_extension("")), &["ts", "tsx", "d.ts"]),
+      Some("mjs") => (Cow::Owned(path.with_extension("")), &["mts", "d.mts"]),
+      Some("cjs") => (Cow::Owned(path.with_extension("")), &["cts", "d.cts"]),
+      _ => (Cow::Borrowed(path), &["ts", "tsx", "d.ts"]),
+    };
+
+    for ext in extensions {
+      if let Some(res) = self.try_suffixes(&path, ext, package, false)? {
+        return Ok(Some(res));
+      }
+    }
+
+    Ok(None)
+  }
+
   fn try_extensions(
     &self,
     path: &Path,
@@ -1035,7 +2088,18 @@ impl<'a> ResolveRequest<'a> {
   }
 
   fn try_file_without_aliases(&self, path: &Path) -> Result<Option<Resolution>, ResolverError> {
-    if self.resolver.cache.is_file(path) {
+    let found = self.resolver.cache.is_file(path);
+    self.trace(|| TraceEvent::File {
+      path: path.to_owned(),
+      found,
+    });
+
+    if found {
+      if self.resolver.flags.contains(Flags::PRESERVE_SYMLINKS) {
+        self.invalidate_on_symlinks(path)?;
+        return Ok(Some(Resolution::Path(path.to_owned())));
+      }
+
       Ok(Some(Resolution::Path(
         self.resolver.cache.canonicalize(path)?,
       )))
@@ -1045,6 +2109,26 @@ impl<'a> ResolveRequest<'a> {
     }
   }
 
+  /// Re-resolves if one of the symlinks in a path that is returned as is changes, e.g. when it is
+  /// pointed to another directory. Changes are reported for the symlink rather than the files in it.
+  fn invalidate_on_symlinks(&self, path: &Path) -> Result<(), ResolverError> {
+    let cache = &self.resolver.cache;
+    if cache.canonicalize(path)? == path {
+      return Ok(());
+    }
+
+    for dir in path.ancestors() {
+      if let (Some(parent), Some(name)) = (dir.parent(), dir.file_name()) {
+        if cache.canonicalize(dir)? != cache.canonicalize(parent)?.join(name) {
+          self.invalidations.invalidate_on_file_change(dir);
+          self.invalidations.invalidate_on_file_create(dir);
+        }
+      }
+    }
+
+    Ok(())
+  }
+
   fn load_directory(
     &self,
     dir: &Path,
@@ -1070,7 +2154,9 @@ impl<'a> ResolveRequest<'a> {
     if self.resolver.flags.contains(Flags::DIR_INDEX) && self.resolver.cache.is_dir(dir) {
       return self.load_file(
         &dir.join(self.resolver.index_file),
-        package.or(parent_package),
+        package
+          .filter(|p| self.has_own_aliases(p))
+          .or(parent_package),
       );
     }
 
@@ -1080,6 +2166,11 @@ impl<'a> ResolveRequest<'a> {
   fn resolve_tsconfig_paths(&self) -> Result<Option<Resolution>, ResolverError> {
     if let Some(tsconfig) = self.tsconfig()? {
       for path in tsconfig.paths(self.specifier) {
+        self.trace(|| TraceEvent::TsConfig {
+          tsconfig: tsconfig.path.clone(),
+          option: "paths",
+          path: path.clone(),
+        });
         // TODO: should aliases apply to tsconfig paths??
         if let Some(res) = self.load_path(&path, None)? {
           return Ok(Some(res));
@@ -1090,6 +2181,24 @@ impl<'a> ResolveRequest<'a> {
     Ok(None)
   }
 
+  /// Returns the conditions to use for the exports and imports fields,
+  /// including any customConditions from tsconfig.json.
+  fn exports_conditions(&self) -> Result<(ExportsCondition, Cow<'a, [String]>), ResolverError> {
+    let mut conditions = self.conditions;
+    let mut custom_conditions = Cow::Borrowed(self.custom_conditions);
+    if let Some(tsconfig) = self.tsconfig()? {
+      for condition in tsconfig.custom_conditions.iter().flatten() {
+        if let Ok(condition) = ExportsCondition::try_from(*condition) {
+          conditions |= condition;
+        } else {
+          custom_conditions.to_mut().push((*condition).to_owned());
+        }
+      }
+    }
+
+    Ok((conditions, custom_conditions))
+  }
+
   fn tsconfig(&self) -> Result<&Option<&TsConfig>, ResolverError> {
     if self.resolver.flags.contains(Flags::TSCONFIG)
       && self
@@ -1147,6 +2256,7 @@ impl<'a> ResolveRequest<'a> {
                   error: Box::new(ResolverError::FileNotFound {
                     relative: path.to_path_buf(),
                     from: tsconfig.compiler_options.path.clone(),
+                    suggestions: Vec::new(),
                   }),
                 });
               }
@@ -1164,6 +2274,10 @@ impl<'a> ResolveRequest<'a> {
                 include_node_modules: Cow::Owned(IncludeNodeModules::default()),
                 conditions: ExportsCondition::TYPES,
                 module_dir_resolver: self.resolver.module_dir_resolver.clone(),
+                module_paths: self.resolver.module_paths.clone(),
+                import_map: self.resolver.import_map.clone(),
+                typescript_version: self.resolver.typescript_version,
+                schemes: self.resolver.schemes.clone(),
               };
 
               let req = ResolveRequest::new(
@@ -1207,7 +2321,10 @@ impl<'a> ResolveRequest<'a> {
 
 #[cfg(test)]
 mod tests {
-  use std::collections::{HashMap, HashSet};
+  use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::Ordering,
+  };
 
   use super::*;
 
@@ -1232,6 +2349,18 @@ mod tests {
     )
   }
 
+  fn resolve(
+    resolver: &Resolver,
+    specifier: &str,
+    from: &Path,
+    specifier_type: SpecifierType,
+  ) -> Result<Resolution, ResolverError> {
+    resolver
+      .resolve(specifier, from, specifier_type)
+      .result
+      .map(|res| res.0)
+  }
+
   #[test]
   fn relative() {
     assert_eq!(
@@ -1309,7 +2438,8 @@ mod tests {
         .unwrap_err(),
       ResolverError::FileNotFound {
         relative: "bar?foo=2".into(),
-        from: root().join("foo.js")
+        from: root().join("foo.js"),
+        suggestions: vec![]
       },
     );
     assert_eq!(
@@ -1546,7 +2676,8 @@ mod tests {
       ResolverError::ModuleSubpathNotFound {
         module: "foo".into(),
         path: root().join("node_modules/foo/with%20space.mjs"),
-        package_path: root().join("node_modules/foo/package.json")
+        package_path: root().join("node_modules/foo/package.json"),
+        suggestions: vec!["foo/with space.mjs".into()]
       },
     );
     assert_eq!(
@@ -1571,7 +2702,8 @@ mod tests {
         .result
         .unwrap_err(),
       ResolverError::ModuleNotFound {
-        module: "@scope/pkg?foo=2".into()
+        module: "@scope/pkg?foo=2".into(),
+        suggestions: vec![]
       },
     );
 
@@ -1656,142 +2788,348 @@ mod tests {
   }
 
   #[test]
-  fn local_aliases() {
+  fn browser_field_spec() {
+    // The package.json browser field spec, as implemented by browser-resolve.
+    // See https://github.com/defunctzombie/package-browser-field-spec. browser-resolve's own
+    // fixtures are not vendored here, so its cases are adapted to the fixtures in browser-field.
+    let dir = root().join("browser-field");
+    let modules = dir.join("node_modules");
+    let resolver = test_resolver();
+
+    // An extensionless key replaces the main entry.
     assert_eq!(
-      test_resolver()
+      resolver
         .resolve(
-          "package-alias/foo",
-          &root().join("foo.js"),
-          SpecifierType::Esm
+          "browser-main-extensionless",
+          &dir.join("index.js"),
+          SpecifierType::Cjs
         )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("node_modules/package-alias/bar.js"))
+      Resolution::Path(modules.join("browser-main-extensionless/browser.js"))
     );
+
+    // The default index file can be replaced.
     assert_eq!(
-      test_resolver()
+      resolver
         .resolve(
-          "./foo",
-          &root().join("node_modules/package-alias/browser.js"),
-          SpecifierType::Esm
+          "browser-main-index",
+          &dir.join("index.js"),
+          SpecifierType::Cjs
         )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("node_modules/package-alias/bar.js"))
+      Resolution::Path(modules.join("browser-main-index/browser.js"))
     );
+
+    // A main entry pointing to a directory is replaced via its index file.
     assert_eq!(
-      test_resolver()
+      resolver
         .resolve(
-          "./lib/test",
-          &root().join("node_modules/package-alias-glob/browser.js"),
-          SpecifierType::Esm
+          "browser-main-directory",
+          &dir.join("index.js"),
+          SpecifierType::Cjs
         )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("node_modules/package-alias-glob/src/test.js"))
+      Resolution::Path(modules.join("browser-main-directory/browser.js"))
     );
+
+    // An extensionless key matches specifiers with an extension.
     assert_eq!(
-      test_resolver()
+      resolver
         .resolve(
-          "package-browser-exclude",
-          &root().join("foo.js"),
-          SpecifierType::Esm
+          "./foo.js",
+          &modules.join("browser-files/index.js"),
+          SpecifierType::Cjs
         )
         .result
         .unwrap()
         .0,
-      Resolution::Empty
+      Resolution::Path(modules.join("browser-files/foo-browser.js"))
     );
     assert_eq!(
-      test_resolver()
+      resolver
         .resolve(
-          "./lib/test",
-          &root().join("node_modules/package-alias-glob/index.js"),
-          SpecifierType::Esm
+          "browser-files/foo.js",
+          &dir.join("index.js"),
+          SpecifierType::Cjs
         )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("node_modules/package-alias-glob/src/test.js"))
-    );
-
-    let invalidations = test_resolver()
-      .resolve(
-        "package-alias/foo",
-        &root().join("foo.js"),
-        SpecifierType::Esm,
-      )
-      .invalidations;
-    assert_eq!(
-      invalidations
-        .invalidate_on_file_create
-        .into_iter()
-        .collect::<HashSet<_>>(),
-      HashSet::from([FileCreateInvalidation::FileName {
-        file_name: "node_modules/package-alias".into(),
-        above: root()
-      },])
-    );
-    assert_eq!(
-      invalidations
-        .invalidate_on_file_change
-        .into_iter()
-        .collect::<HashSet<_>>(),
-      HashSet::from([
-        root().join("node_modules/package-alias/package.json"),
-        root().join("package.json"),
-        root().join("tsconfig.json")
-      ])
+      Resolution::Path(modules.join("browser-files/foo-browser.js"))
     );
-  }
 
-  #[test]
-  fn global_aliases() {
+    // A key with an extension matches extensionless specifiers.
     assert_eq!(
-      test_resolver()
-        .resolve("aliased", &root().join("foo.js"), SpecifierType::Esm)
+      resolver
+        .resolve(
+          "./server",
+          &modules.join("browser-files/index.js"),
+          SpecifierType::Cjs
+        )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("node_modules/foo/index.js"))
+      Resolution::Empty
     );
+
+    // Directories can be replaced.
     assert_eq!(
-      test_resolver()
+      resolver
         .resolve(
-          "aliased",
-          &root().join("node_modules/package-alias/foo.js"),
-          SpecifierType::Esm
+          "./lib/server",
+          &modules.join("browser-files/index.js"),
+          SpecifierType::Cjs
         )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("node_modules/foo/index.js"))
+      Resolution::Path(modules.join("browser-files/lib/client/index.js"))
     );
     assert_eq!(
-      test_resolver()
+      resolver
         .resolve(
-          "aliased/bar",
-          &root().join("node_modules/package-alias/foo.js"),
-          SpecifierType::Esm
+          "browser-files/lib/server",
+          &dir.join("index.js"),
+          SpecifierType::Cjs
         )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("node_modules/foo/bar.js"))
+      Resolution::Path(modules.join("browser-files/lib/client/index.js"))
     );
     assert_eq!(
-      test_resolver()
-        .resolve("aliased-file", &root().join("foo.js"), SpecifierType::Esm)
+      resolver
+        .resolve(
+          "./server",
+          &modules.join("browser-files/lib/index.js"),
+          SpecifierType::Cjs
+        )
         .result
         .unwrap()
         .0,
-      Resolution::Path(root().join("bar.js"))
+      Resolution::Path(modules.join("browser-files/lib/client/index.js"))
     );
+
+    // Modules can be replaced within the package.
     assert_eq!(
-      test_resolver()
+      resolver
+        .resolve(
+          "module-a",
+          &modules.join("browser-files/lib/server/index.js"),
+          SpecifierType::Cjs
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(modules.join("browser-files/shims/module-a.js"))
+    );
+
+    // With `Flags::INHERIT_ALIASES`, mappings in the package root apply to files below a nested
+    // package.json without a name.
+    let mut resolver = test_resolver();
+    resolver.flags |= Flags::INHERIT_ALIASES;
+    assert_eq!(
+      resolver
+        .resolve(
+          "./server",
+          &modules.join("browser-nested/lib/index.js"),
+          SpecifierType::Cjs
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(modules.join("browser-nested/lib/client.js"))
+    );
+    assert_eq!(
+      resolver
+        .resolve(
+          "module-a",
+          &modules.join("browser-nested/lib/index.js"),
+          SpecifierType::Cjs
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Empty
+    );
+    assert_eq!(
+      resolver
+        .resolve(
+          "browser-nested/lib/server",
+          &dir.join("index.js"),
+          SpecifierType::Cjs
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(modules.join("browser-nested/lib/client.js"))
+    );
+
+    // Otherwise, the nested package.json has no mappings, so they do not apply.
+    let resolver = test_resolver();
+    assert_eq!(
+      resolver
+        .resolve(
+          "./server",
+          &modules.join("browser-nested/lib/index.js"),
+          SpecifierType::Cjs
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(modules.join("browser-nested/lib/server.js"))
+    );
+    assert!(matches!(
+      resolver
+        .resolve("module-a", &modules.join("browser-nested/lib/index.js"), SpecifierType::Cjs)
+        .result,
+      Err(ResolverError::ModuleNotFound { module, .. }) if module == "module-a"
+    ));
+  }
+
+  #[test]
+  fn local_aliases() {
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "package-alias/foo",
+          &root().join("foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/package-alias/bar.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "./foo",
+          &root().join("node_modules/package-alias/browser.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/package-alias/bar.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "./lib/test",
+          &root().join("node_modules/package-alias-glob/browser.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/package-alias-glob/src/test.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "package-browser-exclude",
+          &root().join("foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Empty
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "./lib/test",
+          &root().join("node_modules/package-alias-glob/index.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/package-alias-glob/src/test.js"))
+    );
+
+    let invalidations = test_resolver()
+      .resolve(
+        "package-alias/foo",
+        &root().join("foo.js"),
+        SpecifierType::Esm,
+      )
+      .invalidations;
+    assert_eq!(
+      invalidations
+        .invalidate_on_file_create
+        .into_iter()
+        .collect::<HashSet<_>>(),
+      HashSet::from([FileCreateInvalidation::FileName {
+        file_name: "node_modules/package-alias".into(),
+        above: root()
+      },])
+    );
+    assert_eq!(
+      invalidations
+        .invalidate_on_file_change
+        .into_iter()
+        .collect::<HashSet<_>>(),
+      HashSet::from([
+        root().join("node_modules/package-alias/package.json"),
+        root().join("package.json"),
+        root().join("tsconfig.json")
+      ])
+    );
+  }
+
+  #[test]
+  fn global_aliases() {
+    assert_eq!(
+      test_resolver()
+        .resolve("aliased", &root().join("foo.js"), SpecifierType::Esm)
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/foo/index.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "aliased",
+          &root().join("node_modules/package-alias/foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/foo/index.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "aliased/bar",
+          &root().join("node_modules/package-alias/foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/foo/bar.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve("aliased-file", &root().join("foo.js"), SpecifierType::Esm)
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("bar.js"))
+    );
+    assert_eq!(
+      test_resolver()
         .resolve(
           "aliased-file",
           &root().join("node_modules/package-alias/foo.js"),
@@ -2132,6 +3470,15 @@ mod tests {
         module: "package-exports".into(),
         package_path: root().join("node_modules/package-exports/package.json"),
         path: root().join("node_modules/package-exports/features/test"),
+        suggestions: vec![
+          "package-exports/features/*".into(),
+          "package-exports/with%20space".into(),
+          "package-exports/invalid".into(),
+          "package-exports/space".into(),
+          "package-exports/missing".into(),
+          "package-exports/foo".into(),
+          "package-exports".into()
+        ]
       },
     );
     assert_eq!(
@@ -2147,6 +3494,15 @@ mod tests {
         module: "package-exports".into(),
         package_path: root().join("node_modules/package-exports/package.json"),
         path: root().join("node_modules/package-exports/features/test"),
+        suggestions: vec![
+          "package-exports/features/*".into(),
+          "package-exports/with%20space".into(),
+          "package-exports/invalid".into(),
+          "package-exports/space".into(),
+          "package-exports/missing".into(),
+          "package-exports/foo".into(),
+          "package-exports".into()
+        ]
       },
     );
     assert_eq!(
@@ -2423,7 +3779,8 @@ mod tests {
         .result
         .unwrap_err(),
       ResolverError::ModuleNotFound {
-        module: "ts-path".into()
+        module: "ts-path".into(),
+        suggestions: vec![]
       },
     );
     assert_eq!(
@@ -2432,7 +3789,8 @@ mod tests {
         .result
         .unwrap_err(),
       ResolverError::ModuleNotFound {
-        module: "ts-path".into()
+        module: "ts-path".into(),
+        suggestions: vec![]
       },
     );
     assert_eq!(
@@ -2543,6 +3901,89 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_tsconfig_root_dirs() {
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "./template",
+          &root().join("tsconfig/root-dirs/src/index.ts"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("tsconfig/root-dirs/generated/template.ts"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "./template",
+          &root().join("tsconfig/root-dirs/src/views/index.ts"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("tsconfig/root-dirs/generated/views/template.ts"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "./missing",
+          &root().join("tsconfig/root-dirs/src/index.ts"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap_err(),
+      ResolverError::FileNotFound {
+        relative: "missing".into(),
+        from: root().join("tsconfig/root-dirs/src/index.ts"),
+        suggestions: vec![]
+      }
+    );
+  }
+
+  #[test]
+  fn test_tsconfig_custom_conditions() {
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "tsconfig-conditions",
+          &root().join("tsconfig/custom-conditions/index.ts"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/tsconfig-conditions/custom.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "#internal",
+          &root().join("tsconfig/custom-conditions/index.ts"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("tsconfig/custom-conditions/custom.js"))
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "tsconfig-conditions",
+          &root().join("foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("node_modules/tsconfig-conditions/index.js"))
+    );
+  }
+
   #[test]
   fn test_tsconfig_parsing() {
     assert_eq!(
@@ -2647,7 +4088,14 @@ mod tests {
         .unwrap_err(),
       ResolverError::FileNotFound {
         relative: "a.js".into(),
-        from: root().join("ts-extensions/index.js")
+        from: root().join("ts-extensions/index.js"),
+        suggestions: vec![
+          "a.cts".into(),
+          "a.mts".into(),
+          "a.ts".into(),
+          "a.tsx".into(),
+          "b.js".into()
+        ]
       },
     );
 
@@ -2685,10 +4133,17 @@ mod tests {
   }
 
   fn resolve_side_effects(specifier: &str, from: &Path) -> bool {
-    let resolver = test_resolver();
-    let resolved = resolver
-      .resolve(specifier, from, SpecifierType::Esm)
-      .result
+    explain_side_effects(&test_resolver(), specifier, from).side_effects
+  }
+
+  fn explain_side_effects(
+    resolver: &Resolver,
+    specifier: &str,
+    from: &Path,
+  ) -> SideEffectsResolution {
+    let resolved = resolver
+      .resolve(specifier, from, SpecifierType::Esm)
+      .result
       .unwrap()
       .0;
 
@@ -2745,6 +4200,82 @@ mod tests {
     ));
   }
 
+  #[test]
+  fn test_side_effects_reason() {
+    let mut resolver = test_resolver();
+    let from = root().join("foo.js");
+    let package_path = |name: &str| Some(root().join("node_modules").join(name));
+    assert_eq!(
+      explain_side_effects(&resolver, "foo", &from),
+      SideEffectsResolution {
+        side_effects: true,
+        reason: SideEffectsReason::FieldAbsent,
+        package_path: package_path("foo/package.json"),
+        inherited: false,
+      }
+    );
+    assert_eq!(
+      explain_side_effects(
+        &resolver,
+        "side-effects-package-redirect-down/foo/bar",
+        &from
+      ),
+      SideEffectsResolution {
+        side_effects: false,
+        reason: SideEffectsReason::Boolean,
+        package_path: package_path("side-effects-package-redirect-down/foo/bar/baz/package.json"),
+        inherited: false,
+      }
+    );
+    assert_eq!(
+      explain_side_effects(&resolver, "side-effects-false-glob/a/index.js", &from),
+      SideEffectsResolution {
+        side_effects: true,
+        reason: SideEffectsReason::GlobMatched {
+          glob: "a/*.js".into()
+        },
+        package_path: package_path("side-effects-false-glob/package.json"),
+        inherited: false,
+      }
+    );
+
+    // By default, a nested package.json without a "sideEffects" field means all files have side effects.
+    assert_eq!(
+      explain_side_effects(&resolver, "side-effects-nested/dist/esm/index.js", &from),
+      SideEffectsResolution {
+        side_effects: true,
+        reason: SideEffectsReason::FieldAbsent,
+        package_path: package_path("side-effects-nested/dist/esm/package.json"),
+        inherited: false,
+      }
+    );
+
+    // Optionally, one that does not start a new package inherits the "sideEffects" field of its package.
+    resolver.flags |= Flags::INHERIT_SIDE_EFFECTS;
+    assert_eq!(
+      explain_side_effects(&resolver, "side-effects-nested/dist/esm/index.js", &from),
+      SideEffectsResolution {
+        side_effects: false,
+        reason: SideEffectsReason::NoGlobMatched {
+          globs: vec!["*.css".into()]
+        },
+        package_path: package_path("side-effects-nested/package.json"),
+        inherited: true,
+      }
+    );
+    assert_eq!(
+      explain_side_effects(&resolver, "side-effects-nested/dist/esm/style.css", &from),
+      SideEffectsResolution {
+        side_effects: true,
+        reason: SideEffectsReason::GlobMatched {
+          glob: "*.css".into()
+        },
+        package_path: package_path("side-effects-nested/package.json"),
+        inherited: true,
+      }
+    );
+  }
+
   #[test]
   fn test_include_node_modules() {
     let mut resolver = test_resolver();
@@ -2807,6 +4338,1156 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_pnp() {
+    let mut resolver = Resolver::parcel(
+      root().into(),
+      CacheCow::Owned(Cache::new(Arc::new(PnpFileSystem::default()))),
+    );
+    resolver.flags |= Flags::PNP;
+
+    let pnp = root().join("pnp");
+    let foo = pnp.join(".yarn/cache/foo-npm-1.0.0-abc123.zip/node_modules/foo");
+    assert_eq!(
+      resolve(&resolver, "foo", &pnp.join("index.js"), SpecifierType::Esm),
+      Ok(Resolution::Path(foo.join("main.js")))
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "foo/sub",
+        &pnp.join("index.js"),
+        SpecifierType::Esm
+      ),
+      Ok(Resolution::Path(foo.join("sub.js")))
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "renamed",
+        &pnp.join("index.js"),
+        SpecifierType::Esm
+      ),
+      Ok(Resolution::Path(foo.join("main.js")))
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "linked",
+        &pnp.join("index.js"),
+        SpecifierType::Esm
+      ),
+      Ok(Resolution::Path(pnp.join("packages/linked/index.js")))
+    );
+    assert_eq!(
+      resolve(&resolver, "./sub", &foo.join("main.js"), SpecifierType::Esm),
+      Ok(Resolution::Path(foo.join("sub.js")))
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "foo",
+        &pnp.join("packages/linked/index.js"),
+        SpecifierType::Esm
+      ),
+      Ok(Resolution::Path(foo.join("main.js")))
+    );
+    // Undeclared dependencies fall back to the top-level workspace, unless excluded.
+    assert_eq!(
+      resolve(
+        &resolver,
+        "linked",
+        &foo.join("main.js"),
+        SpecifierType::Esm
+      ),
+      Ok(Resolution::Path(pnp.join("packages/linked/index.js")))
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "renamed",
+        &pnp.join("packages/linked/index.js"),
+        SpecifierType::Esm
+      ),
+      Err(ResolverError::ModuleNotFound {
+        module: "renamed".into(),
+        suggestions: vec![]
+      })
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "missing-peer",
+        &foo.join("main.js"),
+        SpecifierType::Esm
+      ),
+      Err(ResolverError::ModuleNotFound {
+        module: "missing-peer".into(),
+        suggestions: vec![]
+      })
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "undeclared",
+        &pnp.join("index.js"),
+        SpecifierType::Esm
+      ),
+      Err(ResolverError::ModuleNotFound {
+        module: "undeclared".into(),
+        suggestions: vec![]
+      })
+    );
+
+    let invalidations = resolver
+      .resolve("foo", &pnp.join("index.js"), SpecifierType::Esm)
+      .invalidations;
+    let invalidate_on_file_change = invalidations
+      .invalidate_on_file_change
+      .into_iter()
+      .collect::<HashSet<_>>();
+    assert!(invalidate_on_file_change.contains(&pnp.join(".pnp.cjs")));
+    assert!(invalidate_on_file_change.contains(&pnp.join(".pnp.data.json")));
+    assert!(invalidate_on_file_change.contains(&foo.join("package.json")));
+  }
+
+  #[test]
+  fn test_case_sensitive() {
+    /// A case-insensitive file system, like the defaults on macOS and Windows.
+    struct CaseInsensitiveFileSystem;
+
+    impl CaseInsensitiveFileSystem {
+      fn actual_path(path: &Path) -> PathBuf {
+        let mut actual_path = PathBuf::new();
+        for component in path.components() {
+          let entry = std::fs::read_dir(&actual_path)
+            .ok()
+            .and_then(|mut entries| {
+              entries.find_map(|entry| {
+                let name = entry.ok()?.file_name();
+                name
+                  .eq_ignore_ascii_case(component.as_os_str())
+                  .then_some(name)
+              })
+            });
+          match entry {
+            Some(name) => actual_path.push(name),
+            None => actual_path.push(component),
+          }
+        }
+        actual_path
+      }
+    }
+
+    impl FileSystem for CaseInsensitiveFileSystem {
+      fn canonicalize(
+        &self,
+        path: &Path,
+        _cache: &FileSystemRealPathCache,
+      ) -> std::io::Result<PathBuf> {
+        // The requested casing is preserved.
+        Ok(path.to_owned())
+      }
+
+      fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(Self::actual_path(path))
+      }
+
+      fn is_file(&self, path: &Path) -> bool {
+        Self::actual_path(path).is_file()
+      }
+
+      fn is_dir(&self, path: &Path) -> bool {
+        Self::actual_path(path).is_dir()
+      }
+
+      fn read_dir(&self, path: &Path) -> std::io::Result<Vec<String>> {
+        OsFileSystem.read_dir(&Self::actual_path(path))
+      }
+    }
+
+    let mut resolver = Resolver::parcel(
+      root().into(),
+      CacheCow::Owned(Cache::new(Arc::new(CaseInsensitiveFileSystem))),
+    );
+    assert_eq!(
+      resolver
+        .resolve(
+          "./case-sensitivity/button",
+          &root().join("foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("case-sensitivity/button.tsx"))
+    );
+
+    resolver.flags |= Flags::CASE_SENSITIVE;
+    assert_eq!(
+      resolver
+        .resolve(
+          "./case-sensitivity/Button",
+          &root().join("foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root().join("case-sensitivity/Button.tsx"))
+    );
+    let res = resolver.resolve(
+      "./case-sensitivity/button",
+      &root().join("foo.js"),
+      SpecifierType::Esm,
+    );
+    assert_eq!(
+      res.result.unwrap_err(),
+      ResolverError::IncorrectCase {
+        path: root().join("case-sensitivity/button.tsx"),
+        actual_path: root().join("case-sensitivity/Button.tsx")
+      }
+    );
+    assert!(res
+      .invalidations
+      .invalidate_on_file_create
+      .into_iter()
+      .any(|i| i == FileCreateInvalidation::Path(root().join("case-sensitivity/button.tsx"))));
+    assert_eq!(
+      resolver
+        .resolve(
+          "./Case-Sensitivity/Button.tsx",
+          &root().join("foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap_err(),
+      ResolverError::IncorrectCase {
+        path: root().join("Case-Sensitivity/Button.tsx"),
+        actual_path: root().join("case-sensitivity/Button.tsx")
+      }
+    );
+  }
+
+  #[test]
+  fn test_suggestions() {
+    assert_eq!(
+      test_resolver()
+        .resolve(
+          "./suggestions/button",
+          &root().join("foo.js"),
+          SpecifierType::Esm
+        )
+        .result
+        .unwrap_err(),
+      ResolverError::FileNotFound {
+        relative: "suggestions/button".into(),
+        from: root().join("foo.js"),
+        suggestions: vec!["suggestions/Button.tsx".into()]
+      },
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve("package-exprts", &root().join("foo.js"), SpecifierType::Esm)
+        .result
+        .unwrap_err(),
+      ResolverError::ModuleNotFound {
+        module: "package-exprts".into(),
+        suggestions: vec!["package-exports".into(), "package-types".into()]
+      },
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve("@scope/pgk", &root().join("foo.js"), SpecifierType::Esm)
+        .result
+        .unwrap_err(),
+      ResolverError::ModuleNotFound {
+        module: "@scope/pgk".into(),
+        suggestions: vec!["@scope/pkg".into()]
+      },
+    );
+    assert_eq!(
+      test_resolver()
+        .resolve("foo/baz.ts", &root().join("foo.js"), SpecifierType::Esm)
+        .result
+        .unwrap_err(),
+      ResolverError::ModuleSubpathNotFound {
+        module: "foo".into(),
+        path: root().join("node_modules/foo/baz.ts"),
+        package_path: root().join("node_modules/foo/package.json"),
+        suggestions: vec!["foo/baz.js".into(), "foo/bar.js".into()]
+      },
+    );
+  }
+
+  #[test]
+  fn test_trace() {
+    let res = test_resolver().resolve_with_options(
+      "aliased",
+      &root().join("foo.js"),
+      SpecifierType::Esm,
+      ResolveOptions {
+        trace: true,
+        ..Default::default()
+      },
+    );
+    assert_eq!(
+      res.result.unwrap().0,
+      Resolution::Path(root().join("node_modules/foo/index.js"))
+    );
+    assert_eq!(
+      res.trace.first(),
+      Some(&TraceEvent::Request {
+        specifier: "aliased".into(),
+        from: root().join("foo.js")
+      })
+    );
+    assert!(res.trace.contains(&TraceEvent::Alias {
+      package_path: root().join("package.json"),
+      field: "alias",
+      specifier: "aliased".into(),
+      value: "foo".into()
+    }));
+    assert!(res.trace.contains(&TraceEvent::Request {
+      specifier: "foo".into(),
+      from: root().join("package.json")
+    }));
+    assert!(res.trace.contains(&TraceEvent::PackageDirectory {
+      module: "foo".into(),
+      path: root().join("node_modules/foo"),
+      found: true
+    }));
+    assert_eq!(
+      res.trace.last(),
+      Some(&TraceEvent::File {
+        path: root().join("node_modules/foo/index.js"),
+        found: true
+      })
+    );
+
+    let res = test_resolver().resolve_with_options(
+      "package-exports/foo",
+      &root().join("foo.js"),
+      SpecifierType::Esm,
+      ResolveOptions {
+        trace: true,
+        ..Default::default()
+      },
+    );
+    assert!(res.trace.contains(&TraceEvent::PackageExports {
+      package_path: root().join("node_modules/package-exports/package.json"),
+      field: "exports",
+      subpath: "foo".into(),
+      conditions: vec!["import".into(), "module".into()],
+      target: root()
+        .join("node_modules/package-exports/foo.mjs")
+        .to_string_lossy()
+        .into_owned()
+    }));
+
+    let res = test_resolver().resolve_with_options(
+      "./missing",
+      &root().join("foo.js"),
+      SpecifierType::Esm,
+      ResolveOptions {
+        trace: true,
+        ..Default::default()
+      },
+    );
+    assert!(res.result.is_err());
+    assert!(res.trace.contains(&TraceEvent::File {
+      path: root().join("missing.js"),
+      found: false
+    }));
+
+    let res = test_resolver().resolve("aliased", &root().join("foo.js"), SpecifierType::Esm);
+    assert!(res.trace.is_empty());
+  }
+
+  #[test]
+  fn test_schemes() {
+    let mut resolver = test_resolver();
+    // JSR packages are installed in node_modules under the @jsr scope, e.g. @jsr/std__path.
+    resolver.schemes.insert(
+      "jsr".into(),
+      Arc::new(|specifier: &str, _: &Path, _: &Invalidations| {
+        let specifier = specifier.trim_start_matches('@').replacen('/', "__", 1);
+        Ok(SchemeResolution::Specifier(format!("@jsr/{}", specifier)))
+      }),
+    );
+    resolver.schemes.insert(
+      "workspace".into(),
+      Arc::new(|specifier: &str, _: &Path, invalidations: &Invalidations| {
+        invalidations.invalidate_on_file_change(&root().join("package.json"));
+        Ok(SchemeResolution::Path(root().join(specifier)))
+      }),
+    );
+    resolver.schemes.insert(
+      "loop".into(),
+      Arc::new(|specifier: &str, _: &Path, _: &Invalidations| {
+        Ok(SchemeResolution::Specifier(format!("loop:{}", specifier)))
+      }),
+    );
+    resolver.schemes.insert(
+      "bun".into(),
+      Arc::new(|specifier: &str, _: &Path, _: &Invalidations| {
+        Ok(SchemeResolution::Resolution(Resolution::Builtin(format!(
+          "bun:{}",
+          specifier
+        ))))
+      }),
+    );
+    let from = root().join("foo.js");
+
+    assert_eq!(
+      resolve(&resolver, "jsr:@std/path", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("node_modules/@jsr/std__path/mod.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "jsr:@std/path/posix", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("node_modules/@jsr/std__path/posix.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "jsr:@std/path", &from, SpecifierType::Cjs).unwrap(),
+      Resolution::Path(root().join("node_modules/@jsr/std__path/mod.js"))
+    );
+    // The returned specifier is resolved with the original specifier type, so it is relative in URLs.
+    assert_eq!(
+      resolve(&resolver, "jsr:@std/path", &from, SpecifierType::Url).unwrap_err(),
+      ResolverError::FileNotFound {
+        relative: "@jsr/std__path".into(),
+        from: root().join("foo.js"),
+        suggestions: vec![]
+      }
+    );
+    assert_eq!(
+      resolve(&resolver, "workspace:bar", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("bar.js"))
+    );
+    let res = resolver.resolve("workspace:bar", &root().join("foo.js"), SpecifierType::Esm);
+    assert!(res
+      .invalidations
+      .invalidate_on_file_change
+      .contains(&root().join("package.json")));
+    // Custom schemes returned by a custom scheme are not resolved again.
+    assert_eq!(
+      resolve(&resolver, "loop:foo", &from, SpecifierType::Esm).unwrap_err(),
+      ResolverError::UnknownScheme {
+        scheme: "loop".into()
+      }
+    );
+    assert_eq!(
+      resolve(&resolver, "workspace:missing", &from, SpecifierType::Esm).unwrap_err(),
+      ResolverError::FileNotFound {
+        relative: root().join("missing"),
+        from: root().join("foo.js"),
+        suggestions: vec![]
+      }
+    );
+    assert_eq!(
+      resolve(&resolver, "bun:sqlite", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Builtin("bun:sqlite".into())
+    );
+    assert_eq!(
+      resolve(&resolver, "deno:foo", &from, SpecifierType::Esm).unwrap_err(),
+      ResolverError::UnknownScheme {
+        scheme: "deno".into()
+      }
+    );
+
+    // Registered schemes take precedence over the builtin ones.
+    resolver.schemes.insert(
+      "npm".into(),
+      Arc::new(|_: &str, _: &Path, _: &Invalidations| {
+        Ok(SchemeResolution::Resolution(Resolution::External))
+      }),
+    );
+    assert_eq!(
+      resolver
+        .resolve("npm:foo", &root().join("foo.js"), SpecifierType::Esm)
+        .result
+        .unwrap()
+        .0,
+      Resolution::External
+    );
+  }
+
+  #[test]
+  fn test_import_map() {
+    let path = root().join("import-map/importmap.json");
+    let mut resolver = test_resolver();
+    resolver.import_map = Some(path.clone());
+
+    let app = root().join("import-map/src/app.js");
+    let legacy = root().join("import-map/src/legacy/index.js");
+
+    assert_eq!(
+      resolve(&resolver, "preact", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("import-map/vendor/preact.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "lodash/fp", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("import-map/vendor/lodash/fp.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "./local.js", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("import-map/src/replaced.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "cdn", &app, SpecifierType::Esm).unwrap(),
+      Resolution::External
+    );
+    assert_eq!(
+      resolve(&resolver, "preact", &legacy, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("import-map/vendor/preact-legacy.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "foo", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root().join("node_modules/foo/index.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "lodash/missing", &app, SpecifierType::Esm).unwrap_err(),
+      ResolverError::FileNotFound {
+        relative: root().join("import-map/vendor/lodash/missing"),
+        from: path.clone(),
+        suggestions: vec![]
+      }
+    );
+    assert_eq!(
+      resolve(&resolver, "package-main", &app, SpecifierType::Esm).unwrap_err(),
+      ResolverError::ImportMapError {
+        import_map: path.clone(),
+        key: "package-main".into(),
+        error: ImportMapError::NullEntry
+      }
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "lodash/../../src/app.js",
+        &app,
+        SpecifierType::Esm
+      )
+      .unwrap_err(),
+      ResolverError::ImportMapError {
+        import_map: path.clone(),
+        key: "lodash/".into(),
+        error: ImportMapError::BacktrackingSpecifier
+      }
+    );
+
+    let res = resolver.resolve_with_options(
+      "preact",
+      &root().join("import-map/src/app.js"),
+      SpecifierType::Esm,
+      ResolveOptions {
+        trace: true,
+        ..Default::default()
+      },
+    );
+    assert!(res.trace.contains(&TraceEvent::ImportMap {
+      import_map: path.clone(),
+      key: "preact".into(),
+      target: root()
+        .join("import-map/vendor/preact.js")
+        .to_string_lossy()
+        .into_owned(),
+    }));
+
+    // Specifiers that the import map does not match may be mapped once it changes.
+    let invalidations = resolver
+      .resolve(
+        "foo",
+        &root().join("import-map/src/app.js"),
+        SpecifierType::Esm,
+      )
+      .invalidations;
+    assert!(invalidations.invalidate_on_file_change.contains(&path));
+  }
+
+  #[test]
+  fn test_import_map_invalidation() -> Result<(), Box<dyn std::error::Error>> {
+    use assert_fs::prelude::*;
+
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("a.js").write_str("")?;
+    dir.child("b.js").write_str("")?;
+    dir
+      .child("importmap.json")
+      .write_str(r#"{"imports": {"dep": "./a.js"}}"#)?;
+
+    let root = dir.path().to_owned();
+    let path = root.join("importmap.json");
+    let mut resolver = Resolver::parcel(
+      root.clone().into(),
+      CacheCow::Owned(Cache::new(Arc::new(OsFileSystem))),
+    );
+    resolver.import_map = Some(path.clone());
+    let from = root.join("index.js");
+    assert_eq!(
+      resolver
+        .resolve("dep", &from, SpecifierType::Esm)
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root.join("a.js"))
+    );
+
+    // The import map is read again once it is invalidated.
+    dir
+      .child("importmap.json")
+      .write_str(r#"{"imports": {"dep": "./b.js"}}"#)?;
+    resolver.cache.as_mut().unwrap().invalidate(&path);
+    assert_eq!(
+      resolver
+        .resolve("dep", &from, SpecifierType::Esm)
+        .result
+        .unwrap()
+        .0,
+      Resolution::Path(root.join("b.js"))
+    );
+
+    // Errors in the import map fail resolutions until it is fixed.
+    dir.child("importmap.json").write_str("{")?;
+    resolver.cache.as_mut().unwrap().invalidate(&path);
+    let res = resolver.resolve("dep", &from, SpecifierType::Esm);
+    assert!(matches!(
+      res.result.unwrap_err(),
+      ResolverError::JsonError(..)
+    ));
+    assert!(res.invalidations.invalidate_on_file_change.contains(&path));
+    Ok(())
+  }
+
+  #[test]
+  fn test_typescript_declarations() {
+    let resolver = Resolver::typescript_declarations(
+      root().into(),
+      CacheCow::Owned(Cache::new(Arc::new(OsFileSystem))),
+    );
+    let dir = root().join("typescript-declarations");
+    let from = dir.join("index.ts");
+
+    assert_eq!(
+      resolve(&resolver, "./utils.js", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("utils.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "./utils", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("utils.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "typed", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/typed/lib/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "typings-field", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/typings-field/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "main-only", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/main-only/dist/main.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "untyped", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/@types/untyped/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "missing-package", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/@types/missing-package/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "@scope/pkg", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/@types/scope__pkg/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "exports-types", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/exports-types/types/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "exports-types/sub", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(dir.join("node_modules/exports-types/sub.d.mts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "exports-types/sub", &from, SpecifierType::Cjs).unwrap_err(),
+      ResolverError::PackageJsonError {
+        module: "exports-types".into(),
+        path: dir.join("node_modules/exports-types/package.json"),
+        error: PackageJsonError::PackagePathNotExported
+      }
+    );
+    assert_eq!(
+      resolve(&resolver, "foo", &from, SpecifierType::Esm).unwrap_err(),
+      ResolverError::ModuleSubpathNotFound {
+        module: "foo".into(),
+        path: root().join("node_modules/foo/index"),
+        package_path: root().join("node_modules/foo/package.json"),
+        suggestions: vec![]
+      }
+    );
+  }
+
+  #[test]
+  fn test_types_versions() {
+    let mut resolver = Resolver::typescript_declarations(
+      root().into(),
+      CacheCow::Owned(Cache::new(Arc::new(OsFileSystem))),
+    );
+    let dir = root().join("typescript-declarations");
+    let package_dir = dir.join("node_modules/types-versions");
+    let from = dir.join("index.ts");
+
+    assert_eq!(
+      resolve(&resolver, "types-versions", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(package_dir.join("ts4/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "types-versions/utils/helpers",
+        &from,
+        SpecifierType::Esm
+      )
+      .unwrap(),
+      Resolution::Path(package_dir.join("ts4/utils/helpers.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "types-versions/other", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(package_dir.join("other.d.ts"))
+    );
+
+    resolver.typescript_version = Some(TypeScriptVersion::new(3, 9, 0));
+    assert_eq!(
+      resolve(&resolver, "types-versions", &from, SpecifierType::Esm).unwrap(),
+      Resolution::Path(package_dir.join("ts3.9/index.d.ts"))
+    );
+    assert_eq!(
+      resolve(&resolver, "types-versions/other", &from, SpecifierType::Esm).unwrap_err(),
+      ResolverError::ModuleSubpathNotFound {
+        module: "types-versions".into(),
+        path: package_dir.join("ts3.9/other"),
+        package_path: package_dir.join("package.json"),
+        suggestions: vec![]
+      }
+    );
+
+    // typesVersions is only used when resolving types.
+    let resolver = test_resolver();
+    assert_eq!(
+      resolver
+        .resolve("types-versions", &dir.join("index.ts"), SpecifierType::Esm)
+        .result
+        .unwrap_err(),
+      ResolverError::ModuleSubpathNotFound {
+        module: "types-versions".into(),
+        path: package_dir.join("index"),
+        package_path: package_dir.join("package.json"),
+        suggestions: vec![]
+      }
+    );
+  }
+
+  #[test]
+  fn test_workspaces() {
+    let mut resolver = test_resolver();
+    resolver.flags |= Flags::WORKSPACES;
+    let workspace = root().join("workspace");
+    let app = workspace.join("packages/app/index.js");
+    assert_eq!(
+      resolve(&resolver, "@monorepo/ui", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(workspace.join("packages/ui/src/index.js"))
+    );
+    assert_eq!(
+      resolve(
+        &resolver,
+        "@monorepo/ui/src/button",
+        &app,
+        SpecifierType::Esm
+      )
+      .unwrap(),
+      Resolution::Path(workspace.join("packages/ui/src/button.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "utils/sub", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(workspace.join("packages/utils/sub.js"))
+    );
+    assert!(matches!(
+      resolve(&resolver, "ignored", &app, SpecifierType::Esm),
+      Err(ResolverError::ModuleNotFound { module, .. }) if module == "ignored"
+    ));
+
+    let invalidations = Invalidations::default();
+    resolver
+      .resolve_with_invalidations(
+        "@monorepo/ui",
+        &app,
+        SpecifierType::Esm,
+        &invalidations,
+        ResolveOptions::default(),
+      )
+      .unwrap();
+    assert!(invalidations
+      .invalidate_on_file_change
+      .contains(&workspace.join("package.json")));
+    assert!(invalidations
+      .invalidate_on_file_change
+      .contains(&workspace.join("packages/ui/package.json")));
+    assert!(invalidations
+      .invalidate_on_file_create
+      .contains(&FileCreateInvalidation::Glob(
+        workspace
+          .join("packages/*/package.json")
+          .to_string_lossy()
+          .into_owned()
+      )));
+
+    let pnpm = root().join("workspace-pnpm");
+    let web = pnpm.join("apps/web/index.js");
+    assert_eq!(
+      resolve(&resolver, "@pnpm/core", &web, SpecifierType::Esm).unwrap(),
+      Resolution::Path(pnpm.join("libs/core/index.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "nested-lib", &web, SpecifierType::Esm).unwrap(),
+      Resolution::Path(pnpm.join("libs/nested/lib/index.js"))
+    );
+    assert!(matches!(
+      resolve(&resolver, "fixture", &web, SpecifierType::Esm),
+      Err(ResolverError::ModuleNotFound { module, .. }) if module == "fixture"
+    ));
+
+    // Without the "source" field, packages resolve to their entries.
+    let mut resolver = node_resolver();
+    resolver.flags |= Flags::WORKSPACES;
+    assert_eq!(
+      resolve(&resolver, "@monorepo/ui", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(workspace.join("packages/ui/dist/index.js"))
+    );
+
+    assert!(matches!(
+      resolve(&test_resolver(), "@monorepo/ui", &app, SpecifierType::Esm),
+      Err(ResolverError::ModuleNotFound { module, .. }) if module == "@monorepo/ui"
+    ));
+  }
+
+  #[test]
+  fn test_workspace_versions() -> Result<(), Box<dyn std::error::Error>> {
+    use assert_fs::prelude::*;
+
+    let dir = assert_fs::TempDir::new()?;
+    dir
+      .child("package.json")
+      .write_str(r#"{"workspaces": ["./packages/*/"]}"#)?;
+    dir.child("packages/app/package.json").write_str(
+      r#"{
+        "name": "app",
+        "dependencies": {"lib": "^1.0.0", "ui": "workspace:*"},
+        "devDependencies": {"utils": "~2.1.0"}
+      }"#,
+    )?;
+    dir.child("packages/app/index.js").write_str("")?;
+    dir
+      .child("packages/lib/package.json")
+      .write_str(r#"{"name": "lib", "version": "2.0.0"}"#)?;
+    dir.child("packages/lib/index.js").write_str("")?;
+    dir
+      .child("packages/ui/package.json")
+      .write_str(r#"{"name": "ui", "version": "0.1.0"}"#)?;
+    dir.child("packages/ui/index.js").write_str("")?;
+    dir
+      .child("packages/utils/package.json")
+      .write_str(r#"{"name": "utils", "version": "2.1.3"}"#)?;
+    dir.child("packages/utils/index.js").write_str("")?;
+    dir
+      .child("packages/app/node_modules/lib/package.json")
+      .write_str(r#"{"name": "lib", "version": "1.2.0"}"#)?;
+    dir
+      .child("packages/app/node_modules/lib/index.js")
+      .write_str("")?;
+
+    let root = dir.path().canonicalize()?;
+    let app = root.join("packages/app/index.js");
+    let mut resolver = Resolver::node(
+      root.clone().into(),
+      CacheCow::Owned(Cache::new(Arc::new(OsFileSystem))),
+    );
+    resolver.flags |= Flags::WORKSPACES;
+    // The workspace package does not satisfy the declared range, so the installed one is used.
+    assert_eq!(
+      resolve(&resolver, "lib", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root.join("packages/app/node_modules/lib/index.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "ui", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root.join("packages/ui/index.js"))
+    );
+    assert_eq!(
+      resolve(&resolver, "utils", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root.join("packages/utils/index.js"))
+    );
+
+    // Glob invalidations are built from the normalized patterns.
+    let invalidations = resolver
+      .resolve("ui", &app, SpecifierType::Esm)
+      .invalidations;
+    assert!(invalidations
+      .invalidate_on_file_create
+      .contains(&FileCreateInvalidation::Glob(
+        root
+          .join("packages/*/package.json")
+          .to_string_lossy()
+          .into_owned()
+      )));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_preserve_symlinks() -> Result<(), Box<dyn std::error::Error>> {
+    use assert_fs::prelude::*;
+
+    #[cfg(windows)]
+    if !is_elevated::is_elevated() {
+      println!("skipping symlink tests due to missing permissions");
+      return Ok(());
+    }
+
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("app/index.js").write_str("")?;
+    dir.child("app/node_modules/dep/index.js").write_str("")?;
+    dir.child("packages/pkg/index.js").write_str("")?;
+    dir
+      .child("app/node_modules/pkg")
+      .symlink_to_dir(dir.child("packages/pkg").path())?;
+
+    let root = dir.path().canonicalize()?;
+    let app = root.join("app/index.js");
+    let mut resolver = Resolver::node(
+      root.clone().into(),
+      CacheCow::Owned(Cache::new(Arc::new(OsFileSystem))),
+    );
+    resolver.flags |= Flags::PRESERVE_SYMLINKS;
+    let pkg = root.join("app/node_modules/pkg/index.js");
+    assert_eq!(
+      resolve(&resolver, "pkg", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(pkg.clone())
+    );
+    // Dependencies of the symlinked package are found relative to the symlink.
+    assert_eq!(
+      resolve(&resolver, "dep", &pkg, SpecifierType::Esm).unwrap(),
+      Resolution::Path(root.join("app/node_modules/dep/index.js"))
+    );
+
+    // Re-resolve if the symlink is replaced, since the result goes through it.
+    let symlink = root.join("app/node_modules/pkg");
+    let invalidations = resolver
+      .resolve("pkg", &app, SpecifierType::Esm)
+      .invalidations;
+    assert!(invalidations.invalidate_on_file_change.contains(&symlink));
+    assert!(invalidations
+      .invalidate_on_file_create
+      .contains(&FileCreateInvalidation::Path(symlink.clone())));
+    let invalidations = resolver
+      .resolve("dep", &pkg, SpecifierType::Esm)
+      .invalidations;
+    assert!(!invalidations.invalidate_on_file_change.contains(&symlink));
+
+    let resolver = Resolver::node(
+      root.clone().into(),
+      CacheCow::Owned(Cache::new(Arc::new(OsFileSystem))),
+    );
+    let real = root.join("packages/pkg/index.js");
+    assert_eq!(
+      resolve(&resolver, "pkg", &app, SpecifierType::Esm).unwrap(),
+      Resolution::Path(real.clone())
+    );
+    assert!(matches!(
+      resolve(&resolver, "dep", &real, SpecifierType::Esm),
+      Err(ResolverError::ModuleNotFound { .. })
+    ));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_exports_entries() {
+    let entries = |package: &str| {
+      let dir = root().join("node_modules").join(package);
+      test_resolver()
+        .exports_entries(&dir.join("package.json"))
+        .unwrap()
+        .into_iter()
+        .map(|entry| {
+          (
+            entry.subpath,
+            entry.conditions.join("+"),
+            entry.path.strip_prefix(&dir).unwrap().to_owned(),
+          )
+        })
+        .collect::<Vec<_>>()
+    };
+    let entry = |subpath: &str, conditions: &str, path: &str| {
+      (
+        subpath.to_owned(),
+        conditions.to_owned(),
+        PathBuf::from(path),
+      )
+    };
+
+    assert_eq!(
+      entries("package-exports"),
+      vec![
+        entry(".", "", "main.mjs"),
+        entry("./foo", "", "foo.mjs"),
+        entry("./features/test", "", "features/test.mjs"),
+        entry("./space", "", "with space.mjs"),
+        entry("./with%20space", "", "with space.mjs"),
+        entry("./missing", "", "missing.mjs"),
+        entry("./extensionless-features/test.mjs", "", "features/test.mjs"),
+      ]
+    );
+    assert_eq!(
+      entries("package-conditions"),
+      vec![
+        entry(".", "browser+development+import", "browser-import-dev.mjs"),
+        entry(
+          ".",
+          "browser+development+require",
+          "browser-require-dev.cjs"
+        ),
+        entry(".", "browser+production+import", "browser-import-prod.mjs"),
+        entry(
+          ".",
+          "browser+production+require",
+          "browser-require-prod.cjs"
+        ),
+        entry(".", "node+import", "node-import.mjs"),
+        entry(".", "node+require", "node-require.cjs"),
+      ]
+    );
+    assert_eq!(entries("package-main"), vec![]);
+  }
+
+  #[test]
+  fn test_exports_entries_symlinks() -> Result<(), Box<dyn std::error::Error>> {
+    use assert_fs::prelude::*;
+
+    #[cfg(windows)]
+    if !is_elevated::is_elevated() {
+      println!("skipping symlink tests due to missing permissions");
+      return Ok(());
+    }
+
+    let dir = assert_fs::TempDir::new()?;
+    dir
+      .child("pkg/package.json")
+      .write_str(r#"{"name": "pkg", "exports": {"./*": "./dist/*"}}"#)?;
+    dir.child("pkg/dist/a.js").write_str("")?;
+    dir.child("shared/b.js").write_str("")?;
+    // Symlinked directories are followed, unless they were already listed.
+    dir
+      .child("pkg/dist/shared")
+      .symlink_to_dir(dir.child("shared").path())?;
+    dir
+      .child("pkg/dist/loop")
+      .symlink_to_dir(dir.child("pkg/dist").path())?;
+
+    let root = dir.path().canonicalize()?;
+    let resolver = Resolver::node(
+      root.clone().into(),
+      CacheCow::Owned(Cache::new(Arc::new(OsFileSystem))),
+    );
+    let subpaths: Vec<_> = resolver
+      .exports_entries(&root.join("pkg/package.json"))
+      .unwrap()
+      .into_iter()
+      .map(|entry| entry.subpath)
+      .collect();
+    assert_eq!(subpaths, vec!["./a.js", "./shared/b.js"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_module_paths() {
+    let mut resolver = node_resolver();
+    resolver.module_paths = vec![root().join("node-path"), root().join("node-path-missing")];
+
+    let res = resolver.resolve("global-pkg", &root().join("foo.js"), SpecifierType::Cjs);
+    assert_eq!(
+      res.result.unwrap().0,
+      Resolution::Path(root().join("node-path/global-pkg/main.js"))
+    );
+    // The global folders come from environment variables, which may change between runs.
+    assert!(res
+      .invalidations
+      .invalidate_on_startup
+      .load(Ordering::Relaxed));
+
+    // node_modules directories take precedence over global folders.
+    let res = resolver.resolve("foo", &root().join("foo.js"), SpecifierType::Cjs);
+    assert_eq!(
+      res.result.unwrap().0,
+      Resolution::Path(root().join("node_modules/foo/index.js"))
+    );
+    assert!(!res
+      .invalidations
+      .invalidate_on_startup
+      .load(Ordering::Relaxed));
+
+    let res = resolver.resolve("missing", &root().join("foo.js"), SpecifierType::Cjs);
+    assert!(res
+      .invalidations
+      .invalidate_on_startup
+      .load(Ordering::Relaxed));
+    assert_eq!(
+      res.result.unwrap_err(),
+      ResolverError::ModuleNotFound {
+        module: "missing".into(),
+        suggestions: vec![]
+      }
+    );
+    let invalidate_on_file_create = res
+      .invalidations
+      .invalidate_on_file_create
+      .into_iter()
+      .collect::<HashSet<_>>();
+    assert!(
+      invalidate_on_file_create.contains(&FileCreateInvalidation::Path(
+        root().join("node-path/missing")
+      ))
+    );
+    assert!(
+      invalidate_on_file_create.contains(&FileCreateInvalidation::Path(
+        root().join("node-path-missing/missing")
+      ))
+    );
+  }
+
+  #[test]
+  fn test_global_paths() {
+    let node_path = std::env::join_paths(["/a", "", "/b"]).unwrap();
+    assert_eq!(
+      global_paths(Some(node_path), Some("/home/user".into()), None),
+      vec![
+        PathBuf::from("/a"),
+        PathBuf::from("/b"),
+        PathBuf::from("/home/user/.node_modules"),
+        PathBuf::from("/home/user/.node_libraries"),
+      ]
+    );
+
+    let prefix = if cfg!(windows) {
+      PathBuf::from("/usr/bin/lib/node")
+    } else {
+      PathBuf::from("/usr/lib/node")
+    };
+    assert_eq!(
+      global_paths(None, None, Some("/usr/bin/node".into())),
+      vec![prefix]
+    );
+  }
+
   // #[test]
   // fn test_visitor() {
   //   let resolved = test_resolver().resolve("unified", &root(), SpecifierType::Esm).unwrap();
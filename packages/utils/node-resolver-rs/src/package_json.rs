@@ -13,6 +13,7 @@ use serde::Deserialize;
 use crate::{
   path::resolve_path,
   specifier::{decode_path, Specifier, SpecifierType},
+  types_versions::{self, TypeScriptVersion},
 };
 
 bitflags! {
@@ -35,14 +36,20 @@ pub struct PackageJson<'a> {
   pub path: PathBuf,
   #[serde(default)]
   pub name: &'a str,
+  #[serde(default)]
+  version: &'a str,
   #[serde(rename = "type", default)]
   pub module_type: ModuleType,
   main: Option<&'a str>,
   module: Option<&'a str>,
   tsconfig: Option<&'a str>,
   types: Option<&'a str>,
+  typings: Option<&'a str>,
   #[serde(default)]
   pub source: SourceField<'a>,
+  /// The "source" field of a package that is not symlinked, which is only used for workspace packages.
+  #[serde(skip)]
+  pub(crate) unlinked_source: SourceField<'a>,
   #[serde(default)]
   browser: BrowserField<'a>,
   #[serde(default)]
@@ -53,6 +60,18 @@ pub struct PackageJson<'a> {
   imports: IndexMap<ExportsKey<'a>, ExportsField<'a>>,
   #[serde(default)]
   side_effects: SideEffects<'a>,
+  #[serde(default)]
+  types_versions: TypesVersions,
+  #[serde(default)]
+  workspaces: WorkspacesField<'a>,
+  #[serde(default, borrow)]
+  dependencies: IndexMap<&'a str, &'a str>,
+  #[serde(default, borrow)]
+  dev_dependencies: IndexMap<&'a str, &'a str>,
+  #[serde(default, borrow)]
+  peer_dependencies: IndexMap<&'a str, &'a str>,
+  #[serde(default, borrow)]
+  optional_dependencies: IndexMap<&'a str, &'a str>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
@@ -87,6 +106,67 @@ pub enum SourceField<'a> {
   Bool(bool),
 }
 
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(untagged)]
+enum WorkspacesField<'a> {
+  #[default]
+  None,
+  #[serde(borrow)]
+  Array(Vec<&'a str>),
+  Object {
+    #[serde(default, borrow)]
+    packages: Vec<&'a str>,
+  },
+}
+
+/// The "typesVersions" field, which maps paths within the package for each TypeScript version range.
+/// Like TypeScript, entries that are not a map of paths to arrays of strings are ignored, rather
+/// than failing to parse the whole package.json.
+#[derive(Debug, Default)]
+struct TypesVersions(IndexMap<String, IndexMap<String, Vec<String>>>);
+
+/// A value that is None if it could not be deserialized, so that invalid entries can be skipped.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Lenient<T> {
+  Valid(T),
+  Invalid(serde::de::IgnoredAny),
+}
+
+impl<T> Lenient<T> {
+  fn ok(self) -> Option<T> {
+    match self {
+      Lenient::Valid(value) => Some(value),
+      Lenient::Invalid(_) => None,
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for TypesVersions {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    type Paths = IndexMap<String, Lenient<Vec<String>>>;
+    let ranges: Lenient<IndexMap<String, Lenient<Paths>>> = Deserialize::deserialize(deserializer)?;
+    Ok(TypesVersions(
+      ranges
+        .ok()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(range, paths)| {
+          let paths = paths
+            .ok()?
+            .into_iter()
+            .filter_map(|(path, targets)| Some((path, targets.ok()?)))
+            .collect();
+          Some((range, paths))
+        })
+        .collect(),
+    ))
+  }
+}
+
 #[derive(serde::Deserialize, Debug, Default, PartialEq)]
 #[serde(untagged)]
 pub enum ExportsField<'a> {
@@ -128,26 +208,43 @@ impl Default for ExportsCondition {
 impl TryFrom<&str> for ExportsCondition {
   type Error = ();
   fn try_from(value: &str) -> Result<Self, Self::Error> {
-    Ok(match value {
-      "import" => ExportsCondition::IMPORT,
-      "require" => ExportsCondition::REQUIRE,
-      "module" => ExportsCondition::MODULE,
-      "node" => ExportsCondition::NODE,
-      "browser" => ExportsCondition::BROWSER,
-      "worker" => ExportsCondition::WORKER,
-      "worklet" => ExportsCondition::WORKLET,
-      "electron" => ExportsCondition::ELECTRON,
-      "development" => ExportsCondition::DEVELOPMENT,
-      "production" => ExportsCondition::PRODUCTION,
-      "types" => ExportsCondition::TYPES,
-      "default" => ExportsCondition::DEFAULT,
-      "style" => ExportsCondition::STYLE,
-      "sass" => ExportsCondition::SASS,
-      "less" => ExportsCondition::LESS,
-      "stylus" => ExportsCondition::STYLUS,
-      _ => return Err(()),
+    ExportsCondition::all()
+      .iter()
+      .find(|condition| condition.name() == Some(value))
+      .ok_or(())
+  }
+}
+
+impl ExportsCondition {
+  /// Returns the name of a single condition, as written in package.json.
+  pub fn name(&self) -> Option<&'static str> {
+    Some(match *self {
+      ExportsCondition::IMPORT => "import",
+      ExportsCondition::REQUIRE => "require",
+      ExportsCondition::MODULE => "module",
+      ExportsCondition::NODE => "node",
+      ExportsCondition::BROWSER => "browser",
+      ExportsCondition::WORKER => "worker",
+      ExportsCondition::WORKLET => "worklet",
+      ExportsCondition::ELECTRON => "electron",
+      ExportsCondition::DEVELOPMENT => "development",
+      ExportsCondition::PRODUCTION => "production",
+      ExportsCondition::TYPES => "types",
+      ExportsCondition::DEFAULT => "default",
+      ExportsCondition::STYLE => "style",
+      ExportsCondition::SASS => "sass",
+      ExportsCondition::LESS => "less",
+      ExportsCondition::STYLUS => "stylus",
+      _ => return None,
     })
   }
+
+  /// Returns each of the conditions that are set, in the order of their bits.
+  pub fn iter(self) -> impl Iterator<Item = ExportsCondition> {
+    (0..u16::BITS)
+      .map(|bit| ExportsCondition::from_bits_truncate(1 << bit))
+      .filter(move |condition| !condition.is_empty() && self.contains(*condition))
+  }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -206,6 +303,24 @@ pub enum SideEffects<'a> {
   Array(Vec<&'a str>),
 }
 
+/// Why a file does or does not have side effects, according to the "sideEffects" field of package.json.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum SideEffectsReason {
+  /// There is no package.json above the file.
+  NoPackage,
+  /// The package.json has no "sideEffects" field, so all files are assumed to have side effects.
+  FieldAbsent,
+  /// The file is outside the package directory, so the "sideEffects" field does not apply.
+  OutsidePackage,
+  /// The "sideEffects" field is a boolean, which applies to all files.
+  Boolean,
+  /// A glob in the "sideEffects" field matched the file.
+  GlobMatched { glob: String },
+  /// None of the globs in the "sideEffects" field matched the file.
+  NoGlobMatched { globs: Vec<String> },
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub enum PackageJsonError {
   InvalidPackageTarget,
@@ -214,6 +329,17 @@ pub enum PackageJsonError {
   ImportNotDefined,
 }
 
+/// A target in the "exports" field, with the subpath and conditions that lead to it.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ExportsTarget<'a> {
+  /// The subpath, e.g. "." or "./features/*".
+  pub subpath: String,
+  /// The keys of the condition maps containing the target, outermost first.
+  pub conditions: Vec<&'a str>,
+  /// The target, relative to the package, e.g. "./features/*.js".
+  pub target: &'a str,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ExportsResolution<'a> {
   None,
@@ -236,7 +362,19 @@ impl<'a> PackageJson<'a> {
   }
 
   pub fn source(&self) -> Option<PathBuf> {
-    match &self.source {
+    self.source_entry(&self.source)
+  }
+
+  /// Returns the "source" entry of a package in a workspace. Unlike `source`, this does not
+  /// require the package to be symlinked, since it is resolved from its own directory.
+  pub fn workspace_source(&self) -> Option<PathBuf> {
+    self
+      .source()
+      .or_else(|| self.source_entry(&self.unlinked_source))
+  }
+
+  fn source_entry(&self, source: &SourceField) -> Option<PathBuf> {
+    match source {
       SourceField::None | SourceField::Array(_) | SourceField::Bool(_) => None,
       SourceField::String(source) => Some(resolve_path(&self.path, source)),
       SourceField::Map(map) => match map.get(&Specifier::Package(
@@ -253,6 +391,138 @@ impl<'a> PackageJson<'a> {
     self.exports != ExportsField::None
   }
 
+  /// Returns the patterns of package directories in the "workspaces" field, e.g. "packages/*".
+  pub fn workspaces(&self) -> &[&'a str] {
+    match &self.workspaces {
+      WorkspacesField::None => &[],
+      WorkspacesField::Array(patterns) | WorkspacesField::Object { packages: patterns } => patterns,
+    }
+  }
+
+  /// Returns the version range of a dependency declared in any of the dependency fields.
+  pub fn dependency_range(&self, name: &str) -> Option<&'a str> {
+    [
+      &self.dependencies,
+      &self.dev_dependencies,
+      &self.peer_dependencies,
+      &self.optional_dependencies,
+    ]
+    .into_iter()
+    .find_map(|dependencies| dependencies.get(name).copied())
+  }
+
+  /// Returns whether the package's version satisfies a dependency range. Ranges using the
+  /// "workspace:" protocol always match, and other protocols such as "npm:" or "file:" never do.
+  /// A package without a version is treated as the newest, like in `resolve_types_versions`.
+  pub fn satisfies(&self, range: &str) -> bool {
+    range.starts_with("workspace:") || types_versions::satisfies(range, self.version.parse().ok())
+  }
+
+  /// Returns the subpaths exported by the package, e.g. "." and "./foo".
+  /// Patterns are returned as written, e.g. "./features/*".
+  pub fn exports_subpaths(&self) -> Vec<String> {
+    match &self.exports {
+      ExportsField::None => Vec::new(),
+      ExportsField::String(_) | ExportsField::Array(_) => vec![".".into()],
+      ExportsField::Map(map) => {
+        let subpaths: Vec<String> = map
+          .keys()
+          .filter_map(|key| match key {
+            ExportsKey::Main => Some(".".into()),
+            ExportsKey::Pattern(pattern) => Some(format!("./{}", pattern)),
+            _ => None,
+          })
+          .collect();
+
+        // A map of conditions applies to the main export.
+        if subpaths.is_empty() {
+          vec![".".into()]
+        } else {
+          subpaths
+        }
+      }
+    }
+  }
+
+  /// Returns every target in the "exports" field, with the subpath and conditions that lead to it.
+  /// All items of fallback arrays are included, and targets that are not relative paths are skipped.
+  pub(crate) fn exports_targets(&self) -> Result<Vec<ExportsTarget<'a>>, PackageJsonError> {
+    let mut targets = Vec::new();
+    match &self.exports {
+      ExportsField::Map(map)
+        if map
+          .keys()
+          .any(|key| matches!(key, ExportsKey::Main | ExportsKey::Pattern(..))) =>
+      {
+        for (key, value) in map {
+          let subpath = match key {
+            ExportsKey::Main => ".".into(),
+            ExportsKey::Pattern(pattern) => format!("./{}", pattern),
+            ExportsKey::Condition(..) | ExportsKey::CustomCondition(..) => {
+              return Err(PackageJsonError::InvalidPackageTarget)
+            }
+          };
+          collect_exports_targets(value, &subpath, &mut Vec::new(), &mut targets);
+        }
+      }
+      // A string, array or map of conditions applies to the main export.
+      exports => collect_exports_targets(exports, ".", &mut Vec::new(), &mut targets),
+    }
+
+    Ok(targets)
+  }
+
+  /// Maps a path within the package, e.g. "index.d.ts", via the first entry of the "typesVersions"
+  /// field whose range matches the TypeScript version. Returns None if no entry or path matches.
+  pub fn resolve_types_versions(
+    &self,
+    subpath: &str,
+    version: Option<TypeScriptVersion>,
+  ) -> Option<Vec<PathBuf>> {
+    let (_, paths) = self
+      .types_versions
+      .0
+      .iter()
+      .find(|(range, _)| types_versions::satisfies(range, version))?;
+
+    if let Some(targets) = paths.get(subpath) {
+      return Some(
+        targets
+          .iter()
+          .map(|target| resolve_path(&self.path, target))
+          .collect(),
+      );
+    }
+
+    // Otherwise, the pattern with the longest prefix before the "*" wins.
+    let (targets, matched) = paths
+      .iter()
+      .filter_map(|(key, targets)| {
+        let (prefix, suffix) = key.split_once('*')?;
+        if subpath.len() >= prefix.len() + suffix.len()
+          && subpath.starts_with(prefix)
+          && subpath.ends_with(suffix)
+        {
+          Some((
+            prefix.len(),
+            targets,
+            &subpath[prefix.len()..subpath.len() - suffix.len()],
+          ))
+        } else {
+          None
+        }
+      })
+      .max_by_key(|(len, ..)| *len)
+      .map(|(_, targets, matched)| (targets, matched))?;
+
+    Some(
+      targets
+        .iter()
+        .map(|target| resolve_path(&self.path, target.replacen('*', matched, 1)))
+        .collect(),
+    )
+  }
+
   pub fn resolve_package_exports(
     &self,
     subpath: &'a str,
@@ -508,12 +778,33 @@ impl<'a> PackageJson<'a> {
           None => {}
           res => return res,
         }
+
+        // As in browserify, keys without an extension also match .js and .json files.
+        if let Specifier::Relative(path) = specifier {
+          if matches!(path.extension(), Some(ext) if ext == "js" || ext == "json") {
+            let specifier = Specifier::Relative(Cow::Owned(path.with_extension("")));
+            match self.resolve_alias(browser, &specifier) {
+              None => {}
+              res => return res,
+            }
+          }
+        }
       }
     }
 
     None
   }
 
+  /// Whether this package.json starts a new package. A nested package.json without a name or
+  /// aliases, e.g. one that only sets "type" or "sideEffects" for a directory, does not, so the
+  /// aliases and "sideEffects" field of the enclosing package can optionally apply instead.
+  pub fn starts_package(&self) -> bool {
+    !self.name.is_empty()
+      || !self.alias.is_empty()
+      || matches!(self.browser, BrowserField::Map(_))
+      || matches!(self.source, SourceField::Map(_))
+  }
+
   fn resolve_alias(
     &self,
     map: &'a IndexMap<Specifier<'a>, AliasValue<'a>>,
@@ -638,6 +929,15 @@ impl<'a> PackageJson<'a> {
   }
 
   pub fn has_side_effects(&self, path: &Path) -> bool {
+    self.explain_side_effects(path).0
+  }
+
+  /// Returns whether a file has side effects, along with the reason, e.g. the glob that matched it.
+  pub fn explain_side_effects(&self, path: &Path) -> (bool, SideEffectsReason) {
+    if self.side_effects == SideEffects::None {
+      return (true, SideEffectsReason::FieldAbsent);
+    }
+
     let path = path
       .strip_prefix(self.path.parent().unwrap())
       .ok()
@@ -645,7 +945,7 @@ impl<'a> PackageJson<'a> {
 
     let path = match path {
       Some(p) => p,
-      None => return true,
+      None => return (true, SideEffectsReason::OutsidePackage),
     };
 
     fn side_effects_glob_matches(glob: &str, path: &str) -> bool {
@@ -662,13 +962,29 @@ impl<'a> PackageJson<'a> {
       glob_match(glob.as_ref(), path)
     }
 
-    match &self.side_effects {
-      SideEffects::None => true,
-      SideEffects::Boolean(b) => *b,
-      SideEffects::String(glob) => side_effects_glob_matches(glob, path),
-      SideEffects::Array(globs) => globs
-        .iter()
-        .any(|glob| side_effects_glob_matches(glob, path)),
+    let globs = match &self.side_effects {
+      SideEffects::None => return (true, SideEffectsReason::FieldAbsent),
+      SideEffects::Boolean(b) => return (*b, SideEffectsReason::Boolean),
+      SideEffects::String(glob) => std::slice::from_ref(glob),
+      SideEffects::Array(globs) => globs.as_slice(),
+    };
+
+    match globs
+      .iter()
+      .find(|glob| side_effects_glob_matches(glob, path))
+    {
+      Some(glob) => (
+        true,
+        SideEffectsReason::GlobMatched {
+          glob: glob.to_string(),
+        },
+      ),
+      None => (
+        false,
+        SideEffectsReason::NoGlobMatched {
+          globs: globs.iter().map(|glob| glob.to_string()).collect(),
+        },
+      ),
     }
   }
 }
@@ -732,6 +1048,46 @@ fn pattern_key_compare(a: &str, b: &str) -> Ordering {
   b.len().cmp(&a.len())
 }
 
+fn collect_exports_targets<'a>(
+  target: &ExportsField<'a>,
+  subpath: &str,
+  conditions: &mut Vec<&'a str>,
+  targets: &mut Vec<ExportsTarget<'a>>,
+) {
+  match target {
+    ExportsField::None => {}
+    ExportsField::String(target) => {
+      if target.starts_with("./") {
+        targets.push(ExportsTarget {
+          subpath: subpath.to_owned(),
+          conditions: conditions.clone(),
+          target,
+        });
+      }
+    }
+    ExportsField::Array(items) => {
+      for item in items {
+        collect_exports_targets(item, subpath, conditions, targets);
+      }
+    }
+    ExportsField::Map(map) => {
+      for (key, value) in map {
+        let condition = match key {
+          ExportsKey::Condition(condition) => match condition.name() {
+            Some(name) => name,
+            None => continue,
+          },
+          ExportsKey::CustomCondition(condition) => condition,
+          ExportsKey::Main | ExportsKey::Pattern(..) => continue,
+        };
+        conditions.push(condition);
+        collect_exports_targets(value, subpath, conditions, targets);
+        conditions.pop();
+      }
+    }
+  }
+}
+
 pub struct EntryIter<'a> {
   package: &'a PackageJson<'a>,
   fields: Fields,
@@ -753,6 +1109,9 @@ impl<'a> Iterator for EntryIter<'a> {
       if let Some(types) = self.package.types {
         return Some((resolve_path(&self.package.path, types), "types"));
       }
+      if let Some(typings) = self.package.typings {
+        return Some((resolve_path(&self.package.path, typings), "typings"));
+      }
     }
 
     if self.fields.contains(Fields::BROWSER) {
@@ -1528,6 +1887,38 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_invalid_types_versions() {
+    let mut pkg: PackageJson = serde_json::from_str(
+      r#"{
+        "name": "foo",
+        "typesVersions": {
+          ">=5.0": "ts5/*",
+          ">=4.0": {
+            "*": ["ts4/*"],
+            "invalid": "ts4/invalid.d.ts",
+            "numbers": [1, 2]
+          },
+          "*": null
+        }
+      }"#,
+    )
+    .unwrap();
+    pkg.path = "/foo/package.json".into();
+
+    assert_eq!(
+      pkg.resolve_types_versions("index.d.ts", None),
+      Some(vec![PathBuf::from("/foo/ts4/index.d.ts")])
+    );
+    assert_eq!(
+      pkg.types_versions.0.get(">=4.0").map(|paths| paths.len()),
+      Some(1)
+    );
+
+    let pkg: PackageJson = serde_json::from_str(r#"{"name": "foo", "typesVersions": []}"#).unwrap();
+    assert!(pkg.types_versions.0.is_empty());
+  }
+
   #[allow(clippy::single_range_in_vec_init)]
   #[test]
   fn test_replace_captures() {
@@ -1553,6 +1944,71 @@ mod tests {
     );
   }
 
+  #[test]
+  fn exports_targets() {
+    let pkg = PackageJson {
+      path: "/foo/package.json".into(),
+      name: "foobar",
+      exports: ExportsField::Map(indexmap! {
+        ".".into() => ExportsField::Map(indexmap! {
+          "import".into() => ExportsField::String("./index.mjs"),
+          "custom".into() => ExportsField::Array(vec![ExportsField::String("http://a.com"), ExportsField::String("./custom.js")]),
+          "default".into() => ExportsField::String("./index.js")
+        }),
+        "./utils/*".into() => ExportsField::String("./utils/*.js"),
+        "./internal/*".into() => ExportsField::None
+      }),
+      ..PackageJson::default()
+    };
+
+    let target =
+      |subpath: &str, conditions: Vec<&'static str>, target: &'static str| ExportsTarget {
+        subpath: subpath.into(),
+        conditions,
+        target,
+      };
+    assert_eq!(
+      pkg.exports_targets().unwrap(),
+      vec![
+        target(".", vec!["import"], "./index.mjs"),
+        target(".", vec!["custom"], "./custom.js"),
+        target(".", vec!["default"], "./index.js"),
+        target("./utils/*", vec![], "./utils/*.js"),
+      ]
+    );
+
+    let pkg = PackageJson {
+      path: "/foo/package.json".into(),
+      name: "foobar",
+      exports: ExportsField::Map(indexmap! {
+        "node".into() => ExportsField::String("./node.js"),
+        "default".into() => ExportsField::String("./index.js")
+      }),
+      ..PackageJson::default()
+    };
+    assert_eq!(
+      pkg.exports_targets().unwrap(),
+      vec![
+        target(".", vec!["node"], "./node.js"),
+        target(".", vec!["default"], "./index.js"),
+      ]
+    );
+
+    let pkg = PackageJson {
+      path: "/foo/package.json".into(),
+      name: "foobar",
+      exports: ExportsField::Map(indexmap! {
+        ".".into() => ExportsField::String("./index.js"),
+        "node".into() => ExportsField::String("./node.js")
+      }),
+      ..PackageJson::default()
+    };
+    assert_eq!(
+      pkg.exports_targets(),
+      Err(PackageJsonError::InvalidPackageTarget)
+    );
+  }
+
   #[test]
   fn side_effects_none() {
     let pkg = PackageJson {
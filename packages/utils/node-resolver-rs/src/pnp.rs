@@ -0,0 +1,487 @@
+use std::{
+  borrow::Cow,
+  collections::{HashMap, HashSet},
+  path::{Component, Path, PathBuf},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use dashmap::DashMap;
+use regex::Regex;
+
+use crate::{cache::JsonError, path::normalize_path};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+  fs::{FileSystem, FileSystemRealPathCache, OsFileSystem},
+  zip::ZipArchive,
+};
+
+/// A package locator, i.e. a package name and reference. The top-level workspace has neither.
+type Locator = (Option<String>, Option<String>);
+
+#[derive(serde::Deserialize, Clone)]
+#[serde(untagged)]
+enum DependencyTarget {
+  Reference(String),
+  Alias(String, String),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageInformation {
+  package_location: String,
+  #[serde(default)]
+  package_dependencies: Vec<(String, Option<DependencyTarget>)>,
+  #[serde(default)]
+  discard_from_lookup: bool,
+}
+
+/// The packages registered under a single name, keyed by reference.
+type PackageReferences = Vec<(Option<String>, PackageInformation)>;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LocatorJson {
+  name: String,
+  reference: String,
+}
+
+/// The serialized runtime state, as written to .pnp.data.json or inlined into .pnp.cjs.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedState {
+  #[serde(default)]
+  enable_top_level_fallback: bool,
+  #[serde(default)]
+  fallback_exclusion_list: Vec<(String, Vec<String>)>,
+  #[serde(default)]
+  fallback_pool: Vec<(String, Option<DependencyTarget>)>,
+  #[serde(default)]
+  dependency_tree_roots: Vec<LocatorJson>,
+  ignore_pattern_data: Option<String>,
+  package_registry_data: Vec<(Option<String>, PackageReferences)>,
+}
+
+/// A parsed Yarn Plug'n'Play manifest.
+/// See https://yarnpkg.com/advanced/pnp-spec for details on the resolution algorithm.
+pub struct PnpManifest {
+  /// The path of the .pnp.cjs file.
+  pub path: PathBuf,
+  /// The path of the .pnp.data.json file, if the state is not inlined into .pnp.cjs.
+  pub data_path: Option<PathBuf>,
+  enable_top_level_fallback: bool,
+  ignore_pattern: Option<Regex>,
+  fallback_exclusion_list: HashMap<String, HashSet<String>>,
+  fallback_pool: HashMap<String, Option<DependencyTarget>>,
+  fallback_locators: Vec<Locator>,
+  packages: HashMap<Locator, PackageInformation>,
+  locations: HashMap<PathBuf, Locator>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PnpResolution {
+  /// The directory of the package that the specifier refers to.
+  Package(PathBuf),
+  /// The issuer is not part of the dependency tree, so the normal node_modules lookup applies.
+  Fallback,
+  /// The dependency is not declared by the issuer, or is a missing peer dependency.
+  NotFound,
+}
+
+impl PnpManifest {
+  pub fn parse(path: PathBuf, data_path: Option<PathBuf>, data: &str) -> Result<Self, JsonError> {
+    let json_path = data_path.as_ref().unwrap_or(&path).clone();
+    let state: SerializedState =
+      serde_json::from_str(data).map_err(|e| JsonError::new(json_path.clone(), e))?;
+
+    let ignore_pattern = match state.ignore_pattern_data {
+      Some(pattern) => Some(Regex::new(&pattern).map_err(|e| JsonError {
+        path: json_path,
+        line: 0,
+        column: 0,
+        message: format!("Invalid ignorePatternData: {}", e),
+      })?),
+      None => None,
+    };
+
+    let base = path.parent().unwrap();
+    let mut packages = HashMap::new();
+    let mut locations = HashMap::new();
+    for (name, references) in state.package_registry_data {
+      for (reference, info) in references {
+        let locator = (name.clone(), reference);
+        if !info.discard_from_lookup {
+          locations.insert(
+            normalize_path(&base.join(&info.package_location)),
+            locator.clone(),
+          );
+        }
+        packages.insert(locator, info);
+      }
+    }
+
+    let mut fallback_locators = vec![(None, None)];
+    fallback_locators.extend(
+      state
+        .dependency_tree_roots
+        .into_iter()
+        .map(|l| (Some(l.name), Some(l.reference))),
+    );
+
+    Ok(PnpManifest {
+      enable_top_level_fallback: state.enable_top_level_fallback,
+      ignore_pattern,
+      fallback_exclusion_list: state
+        .fallback_exclusion_list
+        .into_iter()
+        .map(|(name, references)| (name, references.into_iter().collect()))
+        .collect(),
+      fallback_pool: state.fallback_pool.into_iter().collect(),
+      fallback_locators,
+      packages,
+      locations,
+      path,
+      data_path,
+    })
+  }
+
+  /// Extracts the serialized state that Yarn inlines into .pnp.cjs files as a string literal.
+  /// Yarn 2 inlined the state as an object literal instead, which is not supported.
+  pub fn extract_inline_state(source: &str) -> Option<String> {
+    let start = source.find("RAW_RUNTIME_STATE")?;
+    let rest = source[start..].split_once('=')?.1.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+
+    let mut res = String::new();
+    let mut chars = rest[1..].chars();
+    while let Some(c) = chars.next() {
+      match c {
+        '\\' => match chars.next()? {
+          // Line continuation.
+          '\n' => {}
+          'n' => res.push('\n'),
+          'r' => res.push('\r'),
+          't' => res.push('\t'),
+          c => res.push(c),
+        },
+        c if c == quote => return Some(res),
+        c => res.push(c),
+      }
+    }
+
+    None
+  }
+
+  /// Finds the package that owns the given path.
+  fn find_locator(&self, issuer: &Path) -> Option<&Locator> {
+    if let Some(ignore_pattern) = &self.ignore_pattern {
+      let base = self.path.parent().unwrap();
+      if let Ok(relative) = issuer.strip_prefix(base) {
+        if ignore_pattern.is_match(&relative.to_string_lossy().replace('\\', "/")) {
+          return None;
+        }
+      }
+    }
+
+    issuer.ancestors().find_map(|dir| self.locations.get(dir))
+  }
+
+  /// Resolves a package name to the directory of the package, as seen from the issuer.
+  pub fn resolve(&self, module: &str, issuer: &Path) -> PnpResolution {
+    let issuer_locator = match self.find_locator(issuer) {
+      Some(locator) => locator,
+      None => return PnpResolution::Fallback,
+    };
+
+    let issuer_info = &self.packages[issuer_locator];
+    let mut reference = issuer_info
+      .package_dependencies
+      .iter()
+      .find(|(name, _)| name == module)
+      .map(|(_, target)| target.as_ref());
+
+    if reference.is_none() && self.enable_top_level_fallback {
+      let is_excluded = match issuer_locator {
+        (Some(name), Some(reference)) => self
+          .fallback_exclusion_list
+          .get(name)
+          .is_some_and(|references| references.contains(reference)),
+        _ => false,
+      };
+
+      if !is_excluded {
+        reference = self
+          .fallback_locators
+          .iter()
+          .filter_map(|locator| self.packages.get(locator))
+          .find_map(|info| {
+            info
+              .package_dependencies
+              .iter()
+              .find(|(name, target)| name == module && target.is_some())
+              .map(|(_, target)| target.as_ref())
+          })
+          .or_else(|| self.fallback_pool.get(module).map(|target| target.as_ref()));
+      }
+    }
+
+    // A missing reference is an undeclared dependency, and a null reference is a missing peer dependency.
+    let locator = match reference.flatten() {
+      Some(DependencyTarget::Reference(reference)) => {
+        (Some(module.to_owned()), Some(reference.clone()))
+      }
+      Some(DependencyTarget::Alias(name, reference)) => {
+        (Some(name.clone()), Some(reference.clone()))
+      }
+      None => return PnpResolution::NotFound,
+    };
+
+    match self.packages.get(&locator) {
+      Some(info) => PnpResolution::Package(normalize_path(
+        &self.path.parent().unwrap().join(&info.package_location),
+      )),
+      None => PnpResolution::NotFound,
+    }
+  }
+
+  /// Returns the dependencies of the issuer's package with a name similar to the given module.
+  pub fn similar_dependencies(&self, module: &str, issuer: &Path) -> Vec<String> {
+    let info = match self.find_locator(issuer) {
+      Some(locator) => &self.packages[locator],
+      None => return Vec::new(),
+    };
+
+    crate::suggestions::similar(
+      module,
+      info
+        .package_dependencies
+        .iter()
+        .filter(|(_, target)| target.is_some())
+        .map(|(name, _)| name.clone()),
+    )
+  }
+}
+
+/// Maps a path inside a Yarn virtual directory to the path it points to.
+/// Virtual paths have the form `__virtual__/<hash>/<depth>/<subpath>`, where depth is the
+/// number of directories to go up from the directory containing `__virtual__`.
+pub fn resolve_virtual(path: &Path) -> Cow<'_, Path> {
+  let mut components = path.components();
+  let mut base = PathBuf::new();
+  for component in &mut components {
+    if matches!(component, Component::Normal(c) if c == "__virtual__" || c == "$$virtual") {
+      let depth = components
+        .next()
+        .and_then(|_| components.next())
+        .and_then(|c| c.as_os_str().to_str())
+        .and_then(|depth| depth.parse::<usize>().ok());
+
+      if let Some(depth) = depth {
+        for _ in 0..depth {
+          base.pop();
+        }
+        base.push(components.as_path());
+      }
+
+      return Cow::Owned(base);
+    }
+
+    base.push(component);
+  }
+
+  Cow::Borrowed(path)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// A file system that supports the zip archives and virtual directories used by Yarn Plug'n'Play,
+/// on top of another file system.
+pub struct PnpFileSystem<Fs = OsFileSystem> {
+  fs: Fs,
+  archives: DashMap<PathBuf, Arc<ZipArchive>, xxhash_rust::xxh3::Xxh3Builder>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for PnpFileSystem {
+  fn default() -> Self {
+    PnpFileSystem::new(OsFileSystem)
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<Fs: FileSystem> PnpFileSystem<Fs> {
+  pub fn new(fs: Fs) -> Self {
+    PnpFileSystem {
+      fs,
+      archives: Default::default(),
+    }
+  }
+
+  /// Splits a path inside a zip archive into the archive and the path within it.
+  fn find_archive(&self, path: &Path) -> Option<(Arc<ZipArchive>, String)> {
+    let mut archive_path = PathBuf::new();
+    let mut components = path.components();
+    for component in &mut components {
+      archive_path.push(component);
+      if !matches!(component, Component::Normal(c) if c.to_string_lossy().ends_with(".zip")) {
+        continue;
+      }
+
+      if let Some(archive) = self.open_archive(&archive_path) {
+        let name = components
+          .map(|c| c.as_os_str().to_string_lossy())
+          .collect::<Vec<_>>()
+          .join("/");
+        return Some((archive, name));
+      }
+    }
+
+    None
+  }
+
+  /// Returns the archive at a path. Missing or invalid archives are not cached, because they
+  /// may still be written, e.g. while a package is installed.
+  fn open_archive(&self, path: &Path) -> Option<Arc<ZipArchive>> {
+    if let Some(archive) = self.archives.get(path) {
+      return Some(archive.clone());
+    }
+
+    // The archive is read outside of the map's lock, so that other threads are not blocked.
+    if !self.fs.is_file(path) {
+      return None;
+    }
+    let archive = Arc::new(ZipArchive::open(&self.fs, path).ok()?);
+    Some(
+      self
+        .archives
+        .entry(path.to_owned())
+        .or_insert(archive)
+        .clone(),
+    )
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<Fs: FileSystem> FileSystem for PnpFileSystem<Fs> {
+  fn canonicalize(&self, path: &Path, cache: &FileSystemRealPathCache) -> std::io::Result<PathBuf> {
+    // Virtual paths are preserved so that packages with different peer dependencies stay distinct.
+    let resolved = resolve_virtual(path);
+    if matches!(resolved, Cow::Owned(..)) || self.find_archive(path).is_some() {
+      return Ok(normalize_path(path));
+    }
+
+    self.fs.canonicalize(path, cache)
+  }
+
+  fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+    let path = resolve_virtual(path);
+    if let Some((archive, name)) = self.find_archive(&path) {
+      return String::from_utf8(archive.read(&self.fs, &name)?)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+    }
+
+    self.fs.read_to_string(&path)
+  }
+
+  fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+    let path = resolve_virtual(path);
+    if let Some((archive, name)) = self.find_archive(&path) {
+      return archive.read(&self.fs, &name);
+    }
+
+    self.fs.read(&path)
+  }
+
+  fn is_file(&self, path: &Path) -> bool {
+    let path = resolve_virtual(path);
+    if let Some((archive, name)) = self.find_archive(&path) {
+      return archive.is_file(&name);
+    }
+
+    self.fs.is_file(&path)
+  }
+
+  fn is_dir(&self, path: &Path) -> bool {
+    let path = resolve_virtual(path);
+    if let Some((archive, name)) = self.find_archive(&path) {
+      return archive.is_dir(&name);
+    }
+
+    self.fs.is_dir(&path)
+  }
+
+  fn read_dir(&self, path: &Path) -> std::io::Result<Vec<String>> {
+    let path = resolve_virtual(path);
+    if let Some((archive, name)) = self.find_archive(&path) {
+      return Ok(archive.read_dir(&name));
+    }
+
+    self.fs.read_dir(&path)
+  }
+
+  fn invalidate(&self, path: &Path) {
+    self
+      .archives
+      .retain(|archive, _| !archive.starts_with(path));
+    self.fs.invalidate(path);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_resolve_virtual() {
+    assert_eq!(
+      resolve_virtual(Path::new(
+        "/app/.yarn/__virtual__/foo-virtual-abc123/0/cache/foo.zip/node_modules/foo/index.js"
+      )),
+      Path::new("/app/.yarn/cache/foo.zip/node_modules/foo/index.js")
+    );
+    assert_eq!(
+      resolve_virtual(Path::new(
+        "/app/.yarn/__virtual__/foo-virtual-abc123/2/packages/foo"
+      )),
+      Path::new("/packages/foo")
+    );
+    assert_eq!(
+      resolve_virtual(Path::new("/app/node_modules/foo")),
+      Path::new("/app/node_modules/foo")
+    );
+  }
+
+  #[test]
+  fn test_archive_invalidation() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    let archive = dir.path().join("foo.zip");
+    let file = archive.join("node_modules/foo/main.js");
+    let fs = PnpFileSystem::default();
+
+    // Missing archives are not cached, so they are found once they are written.
+    assert!(!fs.is_file(&file));
+    std::fs::copy(
+      Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../node-resolver-core/test/fixture/pnp/.yarn/cache/foo-npm-1.0.0-abc123.zip"),
+      &archive,
+    )?;
+    assert!(fs.is_file(&file));
+    assert!(fs.read_to_string(&file)?.contains("module.exports"));
+
+    std::fs::remove_file(&archive)?;
+    assert!(fs.is_file(&file));
+    fs.invalidate(&archive);
+    assert!(!fs.is_file(&file));
+    Ok(())
+  }
+
+  #[test]
+  fn test_extract_inline_state() {
+    let source = "#!/usr/bin/env node\n/* eslint-disable */\n\"use strict\";\n\nconst RAW_RUNTIME_STATE =\n'{\\\n  \"__info\": [],\\\n  \"name\": \"it\\'s \\\\ escaped\"\\\n}';\n\nfunction $$SETUP_STATE() {}\n";
+    assert_eq!(
+      PnpManifest::extract_inline_state(source).unwrap(),
+      "{  \"__info\": [],  \"name\": \"it's \\ escaped\"}"
+    );
+  }
+}
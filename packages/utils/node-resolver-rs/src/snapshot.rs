@@ -0,0 +1,153 @@
+use std::{
+  io::{Error, ErrorKind, Result},
+  path::{Path, PathBuf},
+};
+
+/// Identifies a cache snapshot, and the version of its format.
+const MAGIC: &[u8; 8] = b"PRSNAP03";
+
+/// Writes the binary format of cache snapshots: integers are little endian,
+/// and strings and lists are prefixed with their length.
+pub(crate) struct SnapshotWriter {
+  data: Vec<u8>,
+}
+
+impl SnapshotWriter {
+  pub fn new() -> Self {
+    SnapshotWriter {
+      data: MAGIC.to_vec(),
+    }
+  }
+
+  pub fn finish(self) -> Vec<u8> {
+    self.data
+  }
+
+  pub fn bool(&mut self, value: bool) {
+    self.data.push(value as u8);
+  }
+
+  pub fn u64(&mut self, value: u64) {
+    self.data.extend_from_slice(&value.to_le_bytes());
+  }
+
+  pub fn len(&mut self, len: usize) {
+    self.u64(len as u64);
+  }
+
+  pub fn str(&mut self, value: &str) {
+    self.len(value.len());
+    self.data.extend_from_slice(value.as_bytes());
+  }
+
+  pub fn path(&mut self, path: &Path) {
+    self.str(&path.to_string_lossy());
+  }
+
+  pub fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+    self.bool(value.is_some());
+    if let Some(value) = value {
+      write(self, value);
+    }
+  }
+}
+
+pub(crate) struct SnapshotReader<'a> {
+  data: &'a [u8],
+}
+
+impl<'a> SnapshotReader<'a> {
+  pub fn new(data: &'a [u8]) -> Result<Self> {
+    match data.strip_prefix(MAGIC) {
+      Some(data) => Ok(SnapshotReader { data }),
+      None => Err(invalid(
+        "not a resolver cache snapshot, or an unsupported version",
+      )),
+    }
+  }
+
+  fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+    if len > self.data.len() {
+      return Err(invalid("unexpected end of snapshot"));
+    }
+
+    let (bytes, rest) = self.data.split_at(len);
+    self.data = rest;
+    Ok(bytes)
+  }
+
+  pub fn bool(&mut self) -> Result<bool> {
+    match self.bytes(1)? {
+      [0] => Ok(false),
+      [1] => Ok(true),
+      _ => Err(invalid("invalid boolean")),
+    }
+  }
+
+  pub fn u64(&mut self) -> Result<u64> {
+    Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+  }
+
+  pub fn len(&mut self) -> Result<usize> {
+    usize::try_from(self.u64()?).map_err(|_| invalid("invalid length"))
+  }
+
+  pub fn str(&mut self) -> Result<&'a str> {
+    let len = self.len()?;
+    std::str::from_utf8(self.bytes(len)?).map_err(|_| invalid("invalid string"))
+  }
+
+  pub fn path(&mut self) -> Result<PathBuf> {
+    Ok(PathBuf::from(self.str()?))
+  }
+
+  pub fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<Option<T>> {
+    if self.bool()? {
+      Ok(Some(read(self)?))
+    } else {
+      Ok(None)
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.data.is_empty()
+  }
+}
+
+fn invalid(message: &str) -> Error {
+  Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_round_trip() {
+    let mut writer = SnapshotWriter::new();
+    writer.bool(true);
+    writer.u64(42);
+    writer.str("hello");
+    writer.path(Path::new("/foo/bar.json"));
+    writer.option(Some(7), |w, v| w.u64(v));
+    writer.option(None::<u64>, |w, v| w.u64(v));
+    let data = writer.finish();
+
+    let mut reader = SnapshotReader::new(&data).unwrap();
+    assert!(reader.bool().unwrap());
+    assert_eq!(reader.u64().unwrap(), 42);
+    assert_eq!(reader.str().unwrap(), "hello");
+    assert_eq!(reader.path().unwrap(), PathBuf::from("/foo/bar.json"));
+    assert_eq!(reader.option(|r| r.u64()).unwrap(), Some(7));
+    assert_eq!(reader.option(|r| r.u64()).unwrap(), None);
+    assert!(reader.is_empty());
+
+    assert!(SnapshotReader::new(b"garbage").is_err());
+    let mut reader = SnapshotReader::new(&data[..data.len() - 3]).unwrap();
+    reader.bool().unwrap();
+    reader.u64().unwrap();
+    reader.str().unwrap();
+    reader.path().unwrap();
+    assert!(reader.option(|r| r.u64()).is_err());
+  }
+}
@@ -0,0 +1,119 @@
+//! Did-you-mean suggestions for resolution errors.
+
+use std::cmp::Ordering;
+
+/// Returns the candidates that are similar to the given name, most similar first.
+/// Candidates are similar if they differ only in case, only in extension, or by a small edit distance.
+pub fn similar<I: IntoIterator<Item = String>>(name: &str, candidates: I) -> Vec<String> {
+  let mut scored: Vec<(usize, String)> = candidates
+    .into_iter()
+    .filter(|candidate| candidate != name)
+    .filter_map(|candidate| score(name, &candidate).map(|score| (score, candidate)))
+    .collect();
+
+  scored.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+  scored.dedup_by(|a, b| a.1 == b.1);
+  scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// Sorts all candidates by similarity to the given name, most similar first.
+pub fn sort_by_similarity(name: &str, candidates: &mut [String]) {
+  candidates.sort_by(|a, b| match (score(name, a), score(name, b)) {
+    (Some(a), Some(b)) => a.cmp(&b),
+    (Some(_), None) => Ordering::Less,
+    (None, Some(_)) => Ordering::Greater,
+    (None, None) => edit_distance(name, a).cmp(&edit_distance(name, b)),
+  });
+}
+
+fn score(name: &str, candidate: &str) -> Option<usize> {
+  let name = name.to_lowercase();
+  let candidate = candidate.to_lowercase();
+  if name == candidate {
+    return Some(0);
+  }
+
+  if stem(&name) == stem(&candidate) {
+    return Some(1);
+  }
+
+  let distance = edit_distance(&name, &candidate);
+  if distance <= (name.chars().count() / 3).max(1) {
+    return Some(1 + distance);
+  }
+
+  None
+}
+
+/// Returns the name without its extension, e.g. "foo" for "foo.js".
+fn stem(name: &str) -> &str {
+  match name.rsplit_once('.') {
+    Some((stem, _)) if !stem.is_empty() => stem,
+    _ => name,
+  }
+}
+
+/// Computes the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut row: Vec<usize> = (0..=b.len()).collect();
+  for (i, a) in a.chars().enumerate() {
+    let mut prev = row[0];
+    row[0] = i + 1;
+    for (j, b) in b.iter().enumerate() {
+      let cur = row[j + 1];
+      row[j + 1] = if a == *b {
+        prev
+      } else {
+        1 + prev.min(row[j]).min(cur)
+      };
+      prev = cur;
+    }
+  }
+
+  row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_similar() {
+    let candidates = || {
+      [
+        "Button.tsx",
+        "button.css",
+        "Input.tsx",
+        "buttons.js",
+        "index.js",
+      ]
+      .iter()
+      .map(|s| s.to_string())
+    };
+
+    assert_eq!(
+      similar("button.tsx", candidates()),
+      vec!["Button.tsx", "button.css", "buttons.js"]
+    );
+    assert_eq!(similar("Input", candidates()), vec!["Input.tsx"]);
+    assert_eq!(similar("textarea", candidates()), Vec::<String>::new());
+    assert_eq!(
+      similar(
+        "loadsh",
+        ["lodash", "lodash-es", "react"]
+          .iter()
+          .map(|s| s.to_string())
+      ),
+      vec!["lodash"]
+    );
+  }
+
+  #[test]
+  fn test_edit_distance() {
+    assert_eq!(edit_distance("", ""), 0);
+    assert_eq!(edit_distance("abc", ""), 3);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("lodash", "loadsh"), 2);
+  }
+}
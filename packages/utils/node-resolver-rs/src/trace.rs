@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use crate::ExportsCondition;
+
+/// A step taken during resolution, recorded when `ResolveOptions::trace` is enabled.
+/// These explain how a specifier was resolved, and which candidates were rejected along the way.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum TraceEvent {
+  /// Started resolving a specifier, either the original one or e.g. the target of an alias.
+  Request { specifier: String, from: PathBuf },
+  /// An entry in the import map matched the specifier.
+  ImportMap {
+    import_map: PathBuf,
+    key: String,
+    target: String,
+  },
+  /// A custom scheme registered on the resolver mapped the specifier.
+  Scheme {
+    scheme: String,
+    specifier: String,
+    target: String,
+  },
+  /// An alias in package.json matched the specifier.
+  Alias {
+    package_path: PathBuf,
+    field: &'static str,
+    specifier: String,
+    value: String,
+  },
+  /// A candidate path from tsconfig.json, via the paths, baseUrl or rootDirs options.
+  TsConfig {
+    tsconfig: PathBuf,
+    option: &'static str,
+    path: PathBuf,
+  },
+  /// A directory that may contain a package, e.g. in node_modules.
+  PackageDirectory {
+    module: String,
+    path: PathBuf,
+    found: bool,
+  },
+  /// An entry point of a package, e.g. from the "source" or "main" field.
+  PackageEntry {
+    package_path: PathBuf,
+    field: &'static str,
+    path: PathBuf,
+  },
+  /// The "exports" or "imports" field in package.json matched, using the given conditions.
+  PackageExports {
+    package_path: PathBuf,
+    field: &'static str,
+    subpath: String,
+    conditions: Vec<String>,
+    target: String,
+  },
+  /// A candidate file, which is rejected if it does not exist.
+  File { path: PathBuf, found: bool },
+}
+
+/// Returns the names of the given conditions, followed by the custom conditions.
+pub(crate) fn condition_names(
+  conditions: ExportsCondition,
+  custom_conditions: &[String],
+) -> Vec<String> {
+  conditions
+    .iter()
+    .filter_map(|condition| condition.name())
+    .map(str::to_owned)
+    .chain(custom_conditions.iter().cloned())
+    .collect()
+}
@@ -20,7 +20,8 @@ pub struct TsConfig<'a> {
   #[serde(skip)]
   paths_base: PathBuf,
   pub module_suffixes: Option<Vec<&'a str>>,
-  // rootDirs??
+  root_dirs: Option<Vec<PathBuf>>,
+  pub custom_conditions: Option<Vec<&'a str>>,
 }
 
 fn deserialize_extends<'a, 'de: 'a, D>(deserializer: D) -> Result<Vec<Specifier<'a>>, D::Error>
@@ -66,6 +67,12 @@ impl<'a> TsConfig<'a> {
       *base_url = Cow::Owned(resolve_path(&self.path, &base_url));
     }
 
+    if let Some(root_dirs) = &mut self.root_dirs {
+      for root_dir in root_dirs {
+        *root_dir = resolve_path(&self.path, &root_dir);
+      }
+    }
+
     if self.paths.is_some() {
       self.paths_base = if let Some(base_url) = &self.base_url {
         base_url.as_ref().to_owned()
@@ -88,6 +95,35 @@ impl<'a> TsConfig<'a> {
     if self.module_suffixes.is_none() {
       self.module_suffixes = extended.module_suffixes.clone();
     }
+
+    if self.root_dirs.is_none() {
+      self.root_dirs = extended.root_dirs.clone();
+    }
+
+    if self.custom_conditions.is_none() {
+      self.custom_conditions = extended.custom_conditions.clone();
+    }
+  }
+
+  /// Returns the other paths that a relative import may refer to via the rootDirs option.
+  /// These directories are merged into a single virtual directory, so a file in one of
+  /// them can import files from the others as if they were in the same place.
+  pub fn root_dirs(&'a self, path: &'a Path) -> impl Iterator<Item = PathBuf> + 'a {
+    let root_dirs = self.root_dirs.as_deref().unwrap_or_default();
+
+    // Use the longest root dir containing the path, like TypeScript.
+    let matched = root_dirs
+      .iter()
+      .filter(|root_dir| path.starts_with(root_dir))
+      .max_by_key(|root_dir| root_dir.as_os_str().len());
+
+    matched.into_iter().flat_map(move |matched| {
+      let rest = path.strip_prefix(matched).unwrap();
+      root_dirs
+        .iter()
+        .filter(move |root_dir| *root_dir != matched)
+        .map(move |root_dir| root_dir.join(rest))
+    })
   }
 
   pub fn paths(&'a self, specifier: &'a Specifier) -> impl Iterator<Item = PathBuf> + 'a {
@@ -304,4 +340,43 @@ mod tests {
     );
     assert_eq!(test("./jquery"), Vec::<PathBuf>::new());
   }
+
+  #[test]
+  fn test_root_dirs() {
+    let mut tsconfig = TsConfig {
+      path: "/foo/tsconfig.json".into(),
+      root_dirs: Some(vec!["src".into(), "src/views".into(), "generated".into()]),
+      ..Default::default()
+    };
+    tsconfig.validate();
+
+    let test = |path: &str| {
+      tsconfig
+        .root_dirs(Path::new(path))
+        .collect::<Vec<PathBuf>>()
+    };
+
+    assert_eq!(
+      test("/foo/src/template"),
+      vec![
+        PathBuf::from("/foo/src/views/template"),
+        PathBuf::from("/foo/generated/template")
+      ]
+    );
+    assert_eq!(
+      test("/foo/src/views/a/template"),
+      vec![
+        PathBuf::from("/foo/src/a/template"),
+        PathBuf::from("/foo/generated/a/template")
+      ]
+    );
+    assert_eq!(
+      test("/foo/generated/template"),
+      vec![
+        PathBuf::from("/foo/src/template"),
+        PathBuf::from("/foo/src/views/template")
+      ]
+    );
+    assert_eq!(test("/foo/other/template"), Vec::<PathBuf>::new());
+  }
 }
@@ -0,0 +1,161 @@
+use std::str::FromStr;
+
+/// A TypeScript version, which selects the entry to use in the "typesVersions" field of package.json.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypeScriptVersion {
+  pub major: u64,
+  pub minor: u64,
+  pub patch: u64,
+}
+
+impl TypeScriptVersion {
+  pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+    TypeScriptVersion {
+      major,
+      minor,
+      patch,
+    }
+  }
+}
+
+impl FromStr for TypeScriptVersion {
+  type Err = ();
+
+  /// Parses a version such as "5.4.2". Missing components are zero, and prerelease tags are ignored.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.split(['-', '+']).next().unwrap_or(s);
+    let mut parts = s.split('.').map(|p| p.parse::<u64>());
+    let major = parts.next().ok_or(())?.map_err(|_| ())?;
+    let minor = parts.next().transpose().map_err(|_| ())?.unwrap_or(0);
+    let patch = parts.next().transpose().map_err(|_| ())?.unwrap_or(0);
+    if parts.next().is_some() {
+      return Err(());
+    }
+
+    Ok(TypeScriptVersion::new(major, minor, patch))
+  }
+}
+
+/// Returns whether a version satisfies an npm-style range, e.g. ">=4.2", ">=3.1 <4" or "~4.0 || ^5".
+/// Without a version, this checks the newest version of TypeScript, so only ranges without an upper bound match.
+pub(crate) fn satisfies(range: &str, version: Option<TypeScriptVersion>) -> bool {
+  range.split("||").any(|range| {
+    let range = range.trim();
+    if let Some((min, max)) = range.split_once(" - ") {
+      return satisfies_comparator(&format!(">={}", min.trim()), version)
+        && satisfies_comparator(&format!("<={}", max.trim()), version);
+    }
+
+    range
+      .split_whitespace()
+      .all(|comparator| satisfies_comparator(comparator, version))
+  })
+}
+
+fn satisfies_comparator(comparator: &str, version: Option<TypeScriptVersion>) -> bool {
+  let (op, partial) = [">=", "<=", ">", "<", "=", "~", "^"]
+    .iter()
+    .find_map(|op| Some((*op, comparator.strip_prefix(op)?)))
+    .unwrap_or(("", comparator));
+
+  let partial: Option<Vec<u64>> = partial
+    .trim_start_matches('v')
+    .split('.')
+    .take_while(|p| !matches!(*p, "" | "x" | "X" | "*"))
+    .map(|p| p.parse().ok())
+    .collect();
+  let partial = match partial {
+    Some(partial) if partial.len() <= 3 => partial,
+    _ => return false,
+  };
+
+  if partial.is_empty() {
+    // A wildcard matches everything, except nothing is less than it.
+    return !matches!(op, "<" | ">");
+  }
+
+  // Each comparator is converted to an inclusive lower bound and an exclusive upper bound.
+  let lower = fill(&partial);
+  let (lower, upper) = match op {
+    ">=" => (Some(lower), None),
+    ">" => (Some(bump(&partial, partial.len() - 1)), None),
+    "<" => (None, Some(lower)),
+    "<=" => (None, Some(bump(&partial, partial.len() - 1))),
+    "~" => (Some(lower), Some(bump(&partial, partial.len().min(2) - 1))),
+    "^" => {
+      // Bump the first non-zero component, or the last one if all are zero.
+      let i = partial
+        .iter()
+        .position(|p| *p != 0)
+        .unwrap_or(partial.len() - 1);
+      (Some(lower), Some(bump(&partial, i)))
+    }
+    _ => (Some(lower), Some(bump(&partial, partial.len() - 1))),
+  };
+
+  match version {
+    Some(version) => lower.is_none_or(|l| version >= l) && upper.is_none_or(|u| version < u),
+    None => upper.is_none(),
+  }
+}
+
+fn fill(partial: &[u64]) -> TypeScriptVersion {
+  let get = |i: usize| partial.get(i).copied().unwrap_or(0);
+  TypeScriptVersion::new(get(0), get(1), get(2))
+}
+
+/// Increments the component at the given index, and resets the following components.
+fn bump(partial: &[u64], index: usize) -> TypeScriptVersion {
+  let mut parts = [0; 3];
+  parts[..index].copy_from_slice(&partial[..index]);
+  parts[index] = partial[index] + 1;
+  TypeScriptVersion::new(parts[0], parts[1], parts[2])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn v(s: &str) -> Option<TypeScriptVersion> {
+    Some(s.parse().unwrap())
+  }
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(v("5.4.2"), Some(TypeScriptVersion::new(5, 4, 2)));
+    assert_eq!(v("4.9"), Some(TypeScriptVersion::new(4, 9, 0)));
+    assert_eq!(v("5.5.0-beta"), Some(TypeScriptVersion::new(5, 5, 0)));
+    assert!("five".parse::<TypeScriptVersion>().is_err());
+    assert!("1.2.3.4".parse::<TypeScriptVersion>().is_err());
+  }
+
+  #[test]
+  fn test_satisfies() {
+    assert!(satisfies("*", v("3.0")));
+    assert!(satisfies("*", None));
+    assert!(satisfies(">=4.2", v("4.2")));
+    assert!(satisfies(">=4.2", v("5.0")));
+    assert!(!satisfies(">=4.2", v("4.1.9")));
+    assert!(satisfies(">=4.2", None));
+    assert!(satisfies(">3.1", v("3.2")));
+    assert!(!satisfies(">3.1", v("3.1.5")));
+    assert!(satisfies("<4", v("3.9")));
+    assert!(!satisfies("<4", v("4.0")));
+    assert!(!satisfies("<4", None));
+    assert!(satisfies("<=4.1", v("4.1.3")));
+    assert!(!satisfies("<=4.1", v("4.2")));
+    assert!(satisfies(">=3.1 <4", v("3.8")));
+    assert!(!satisfies(">=3.1 <4", v("4.0")));
+    assert!(satisfies("~4.1", v("4.1.5")));
+    assert!(!satisfies("~4.1", v("4.2")));
+    assert!(satisfies("^4.1", v("4.9")));
+    assert!(!satisfies("^4.1", v("5.0")));
+    assert!(satisfies("4.1", v("4.1.2")));
+    assert!(!satisfies("4.1", v("4.2")));
+    assert!(satisfies("3.0 - 3.9", v("3.9.1")));
+    assert!(!satisfies("3.0 - 3.9", v("4.0")));
+    assert!(satisfies("<3 || >=4", v("4.5")));
+    assert!(!satisfies("<3 || >=4", v("3.5")));
+    assert!(!satisfies("invalid", v("4.0")));
+  }
+}
@@ -0,0 +1,265 @@
+use std::{
+  borrow::Cow,
+  collections::HashMap,
+  path::{Component, Path, PathBuf},
+};
+
+use glob_match::glob_match;
+
+use crate::{Cache, ResolverError};
+
+/// A monorepo workspace, declared by a pnpm-workspace.yaml file or the "workspaces" field in package.json.
+/// This maps the names of the packages in the workspace to their directories.
+#[derive(Debug)]
+pub struct Workspace {
+  /// The pnpm-workspace.yaml or package.json file that declares the workspace.
+  pub manifest: PathBuf,
+  /// Patterns of package directories relative to the workspace root, e.g. "packages/*".
+  /// Excluded directories start with "!". Leading "./" and trailing slashes are removed.
+  pub patterns: Vec<String>,
+  packages: HashMap<String, PathBuf>,
+}
+
+impl Workspace {
+  pub(crate) fn read(cache: &Cache, manifest: &Path) -> Result<Workspace, ResolverError> {
+    let raw_patterns: Vec<String> = if manifest.ends_with("pnpm-workspace.yaml") {
+      parse_pnpm_workspace(&cache.fs.read_to_string(manifest)?)
+    } else {
+      let package = cache.read_package(Cow::Borrowed(manifest))?;
+      package
+        .workspaces()
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect()
+    };
+
+    let root = manifest.parent().unwrap();
+    let mut dirs = vec![root.to_owned()];
+    let patterns: Vec<String> = raw_patterns
+      .iter()
+      .map(|pattern| normalize_pattern(pattern))
+      .collect();
+    let (excludes, includes): (Vec<_>, Vec<_>) = patterns
+      .iter()
+      .partition(|pattern| pattern.starts_with('!'));
+    for pattern in &includes {
+      let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+      expand(cache, root, &segments, &mut dirs);
+    }
+
+    let mut packages = HashMap::new();
+    for dir in dirs {
+      let relative = relative_path(root, &dir);
+      if excludes
+        .iter()
+        .any(|exclude| glob_match(&exclude[1..], &relative))
+      {
+        continue;
+      }
+
+      let package_path = dir.join("package.json");
+      if !cache.is_file(&package_path) {
+        continue;
+      }
+
+      if let Ok(package) = cache.read_package(Cow::Owned(package_path)) {
+        if !package.name.is_empty() {
+          packages.entry(package.name.to_owned()).or_insert(dir);
+        }
+      }
+    }
+
+    Ok(Workspace {
+      manifest: manifest.to_owned(),
+      patterns,
+      packages,
+    })
+  }
+
+  /// The directory containing the workspace manifest.
+  pub fn root(&self) -> &Path {
+    self.manifest.parent().unwrap()
+  }
+
+  /// Returns the directory of a package in the workspace.
+  pub fn package_dir(&self, name: &str) -> Option<&Path> {
+    self.packages.get(name).map(|dir| dir.as_path())
+  }
+
+  /// Returns the names and directories of the packages in the workspace.
+  pub fn packages(&self) -> impl Iterator<Item = (&str, &Path)> {
+    self
+      .packages
+      .iter()
+      .map(|(name, dir)| (name.as_str(), dir.as_path()))
+  }
+}
+
+/// Returns the patterns in the "packages" list of a pnpm-workspace.yaml file.
+/// This only supports the subset of YAML used by these files in practice:
+/// a block sequence or a flow sequence of optionally quoted strings.
+fn parse_pnpm_workspace(source: &str) -> Vec<String> {
+  let mut patterns = Vec::new();
+  let mut in_packages = false;
+  for line in source.lines() {
+    let line = strip_comment(line);
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    if !line.starts_with([' ', '\t', '-']) {
+      in_packages = false;
+      if let Some(value) = line.strip_prefix("packages:") {
+        let value = value.trim();
+        if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+          patterns.extend(list.split(',').map(unquote).filter(|p| !p.is_empty()));
+        } else {
+          in_packages = value.is_empty();
+        }
+      }
+      continue;
+    }
+
+    if in_packages {
+      if let Some(item) = line.trim_start().strip_prefix('-') {
+        let item = unquote(item);
+        if !item.is_empty() {
+          patterns.push(item);
+        }
+      }
+    }
+  }
+
+  patterns
+}
+
+fn strip_comment(line: &str) -> &str {
+  let mut quote = None;
+  for (i, c) in line.char_indices() {
+    match (c, quote) {
+      ('\'' | '"', None) => quote = Some(c),
+      (c, Some(q)) if c == q => quote = None,
+      ('#', None) if i == 0 || line[..i].ends_with([' ', '\t']) => return &line[..i],
+      _ => {}
+    }
+  }
+  line
+}
+
+fn unquote(value: &str) -> String {
+  let value = value.trim();
+  value
+    .strip_prefix('\'')
+    .and_then(|v| v.strip_suffix('\''))
+    .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')))
+    .unwrap_or(value)
+    .to_owned()
+}
+
+fn normalize_pattern(pattern: &str) -> String {
+  let (negated, pattern) = match pattern.strip_prefix('!') {
+    Some(pattern) => (true, pattern),
+    None => (false, pattern),
+  };
+  let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+  if negated {
+    format!("!{}", pattern)
+  } else {
+    pattern.to_owned()
+  }
+}
+
+/// Finds the directories matching a pattern, split into path segments.
+fn expand(cache: &Cache, dir: &Path, segments: &[&str], dirs: &mut Vec<PathBuf>) {
+  let (segment, rest) = match segments.split_first() {
+    Some(res) => res,
+    None => {
+      if !dirs.iter().any(|d| d == dir) {
+        dirs.push(dir.to_owned());
+      }
+      return;
+    }
+  };
+
+  if *segment == "**" {
+    expand(cache, dir, rest, dirs);
+    for child in child_dirs(cache, dir) {
+      expand(cache, &child, segments, dirs);
+    }
+  } else if segment.contains(['*', '?', '[', '{']) {
+    for child in child_dirs(cache, dir) {
+      let name = child.file_name().unwrap().to_string_lossy();
+      if glob_match(segment, &name) {
+        expand(cache, &child, rest, dirs);
+      }
+    }
+  } else {
+    let child = dir.join(segment);
+    if cache.is_dir(&child) {
+      expand(cache, &child, rest, dirs);
+    }
+  }
+}
+
+/// Returns the subdirectories of a directory, except node_modules and hidden directories.
+fn child_dirs(cache: &Cache, dir: &Path) -> Vec<PathBuf> {
+  let entries = match cache.read_dir(dir) {
+    Some(entries) => entries,
+    None => return Vec::new(),
+  };
+
+  let mut children: Vec<PathBuf> = entries
+    .iter()
+    .filter(|name| *name != "node_modules" && !name.starts_with('.'))
+    .map(|name| dir.join(name))
+    .filter(|child| cache.is_dir(child))
+    .collect();
+  children.sort();
+  children
+}
+
+fn relative_path(root: &Path, dir: &Path) -> String {
+  dir
+    .strip_prefix(root)
+    .unwrap_or(dir)
+    .components()
+    .filter_map(|c| match c {
+      Component::Normal(c) => Some(c.to_string_lossy()),
+      _ => None,
+    })
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_pnpm_workspace() {
+    assert_eq!(
+      parse_pnpm_workspace(
+        r#"
+# The packages in this workspace.
+packages:
+  - 'packages/*'
+  - "apps/**" # All apps
+  - tools/cli
+  - '!**/test/**'
+
+catalog:
+  react: ^18.0.0
+"#
+      ),
+      vec!["packages/*", "apps/**", "tools/cli", "!**/test/**"]
+    );
+    assert_eq!(
+      parse_pnpm_workspace("packages: ['packages/*', \"apps/*\"]\n"),
+      vec!["packages/*", "apps/*"]
+    );
+    assert_eq!(
+      parse_pnpm_workspace("onlyBuiltDependencies:\n  - esbuild\n"),
+      Vec::<String>::new()
+    );
+  }
+}
@@ -0,0 +1,236 @@
+use std::{
+  collections::{HashMap, HashSet},
+  io::{Error, ErrorKind, Read, Result},
+  path::{Path, PathBuf},
+};
+
+use flate2::read::DeflateDecoder;
+
+use crate::FileSystem;
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+
+/// A minimal reader for the zip archives that Yarn stores packages in.
+/// Only the stored and deflate compression methods are supported, which is all Yarn produces.
+/// Only the index of the archive is kept in memory, and each file is read from its own range of the
+/// archive through the given file system.
+pub struct ZipArchive {
+  path: PathBuf,
+  entries: HashMap<String, ZipEntry>,
+  dirs: HashSet<String>,
+}
+
+struct ZipEntry {
+  offset: u64,
+  compressed_size: u64,
+  method: u16,
+}
+
+impl ZipArchive {
+  pub fn open(fs: &dyn FileSystem, path: &Path) -> Result<ZipArchive> {
+    let data = fs.read(path)?;
+
+    // The end of central directory record is at the end of the file, followed by a variable length comment.
+    let eocd = (0..=data.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+      .rev()
+      .take(u16::MAX as usize + 1)
+      .find(|i| read_u32(&data, *i) == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+      .ok_or_else(|| invalid("Missing end of central directory"))?;
+
+    let count = read_u16(&data, eocd + 10).ok_or_else(|| invalid("Invalid zip archive"))?;
+    let size = read_u32(&data, eocd + 12).ok_or_else(|| invalid("Invalid zip archive"))?;
+    let offset = read_u32(&data, eocd + 16).ok_or_else(|| invalid("Invalid zip archive"))?;
+    if offset == u32::MAX || size == u32::MAX {
+      return Err(invalid("Zip64 archives are not supported"));
+    }
+
+    let central_directory = data
+      .get(offset as usize..offset as usize + size as usize)
+      .ok_or_else(|| invalid("Invalid central directory"))?;
+
+    let mut entries = HashMap::with_capacity(count as usize);
+    let mut dirs = HashSet::new();
+    dirs.insert(String::new());
+
+    let mut pos = 0;
+    for _ in 0..count {
+      let (name, entry, len) = read_central_directory_entry(central_directory, pos)
+        .ok_or_else(|| invalid("Invalid central directory entry"))?;
+      pos += len;
+
+      let name = name.trim_start_matches('/');
+      let (dir, is_dir) = match name.strip_suffix('/') {
+        Some(dir) => (dir, true),
+        None => (name.rsplit_once('/').map_or("", |(dir, _)| dir), false),
+      };
+
+      // Directories are not required to have their own entries, so record all ancestors.
+      let mut dir = dir;
+      while dirs.insert(dir.to_owned()) {
+        dir = dir.rsplit_once('/').map_or("", |(parent, _)| parent);
+      }
+
+      if !is_dir {
+        entries.insert(name.to_owned(), entry);
+      }
+    }
+
+    Ok(ZipArchive {
+      path: path.to_owned(),
+      entries,
+      dirs,
+    })
+  }
+
+  pub fn is_file(&self, name: &str) -> bool {
+    self.entries.contains_key(name)
+  }
+
+  pub fn is_dir(&self, name: &str) -> bool {
+    self.dirs.contains(name)
+  }
+
+  /// Returns the names of the files and directories directly inside the given directory.
+  pub fn read_dir(&self, name: &str) -> Vec<String> {
+    let prefix = if name.is_empty() {
+      String::new()
+    } else {
+      format!("{}/", name)
+    };
+
+    let mut children: Vec<String> = self
+      .entries
+      .keys()
+      .chain(self.dirs.iter())
+      .filter_map(|entry| entry.strip_prefix(&prefix))
+      .filter(|child| !child.is_empty() && !child.contains('/'))
+      .map(|child| child.to_owned())
+      .collect();
+    children.sort();
+    children.dedup();
+    children
+  }
+
+  pub fn read(&self, fs: &dyn FileSystem, name: &str) -> Result<Vec<u8>> {
+    let entry = self.entries.get(name).ok_or_else(|| {
+      Error::new(
+        ErrorKind::NotFound,
+        format!("{} not found in archive", name),
+      )
+    })?;
+
+    let header = fs.read_range(&self.path, entry.offset, LOCAL_FILE_HEADER_SIZE)?;
+    if read_u32(&header, 0) != Some(LOCAL_FILE_HEADER_SIGNATURE) {
+      return Err(invalid("Invalid local file header"));
+    }
+
+    // The local header may have a different extra field than the central directory entry.
+    let start = read_u16(&header, 26)
+      .zip(read_u16(&header, 28))
+      .map(|(name_len, extra_len)| {
+        LOCAL_FILE_HEADER_SIZE as u64 + name_len as u64 + extra_len as u64
+      })
+      .ok_or_else(|| invalid("Invalid local file header"))?;
+    let data = fs.read_range(
+      &self.path,
+      entry.offset + start,
+      entry.compressed_size as usize,
+    )?;
+    if data.len() as u64 != entry.compressed_size {
+      return Err(invalid("Invalid zip entry"));
+    }
+
+    match entry.method {
+      0 => Ok(data),
+      8 => {
+        let mut contents = Vec::new();
+        DeflateDecoder::new(&data[..]).read_to_end(&mut contents)?;
+        Ok(contents)
+      }
+      _ => Err(invalid("Unsupported compression method")),
+    }
+  }
+}
+
+/// Reads a central directory file header, returning the file name, the entry, and the header length.
+fn read_central_directory_entry(data: &[u8], pos: usize) -> Option<(&str, ZipEntry, usize)> {
+  if read_u32(data, pos)? != CENTRAL_DIRECTORY_SIGNATURE {
+    return None;
+  }
+
+  let name_len = read_u16(data, pos + 28)? as usize;
+  let extra_len = read_u16(data, pos + 30)? as usize;
+  let comment_len = read_u16(data, pos + 32)? as usize;
+  let name = std::str::from_utf8(data.get(pos + 46..pos + 46 + name_len)?).ok()?;
+  let entry = ZipEntry {
+    method: read_u16(data, pos + 10)?,
+    compressed_size: read_u32(data, pos + 20)? as u64,
+    offset: read_u32(data, pos + 42)? as u64,
+  };
+
+  Some((name, entry, 46 + name_len + extra_len + comment_len))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+  Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn invalid(message: &str) -> Error {
+  Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{FileSystemRealPathCache, OsFileSystem};
+
+  /// Reads ranges from disk, but fails to read whole files.
+  struct RangeOnlyFileSystem;
+
+  impl FileSystem for RangeOnlyFileSystem {
+    fn canonicalize(&self, path: &Path, cache: &FileSystemRealPathCache) -> Result<PathBuf> {
+      OsFileSystem.canonicalize(path, cache)
+    }
+
+    fn read_to_string(&self, _path: &Path) -> Result<String> {
+      Err(ErrorKind::Unsupported.into())
+    }
+
+    fn read(&self, _path: &Path) -> Result<Vec<u8>> {
+      Err(ErrorKind::Unsupported.into())
+    }
+
+    fn read_range(&self, path: &Path, offset: u64, len: usize) -> Result<Vec<u8>> {
+      OsFileSystem.read_range(path, offset, len)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+      OsFileSystem.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+      OsFileSystem.is_dir(path)
+    }
+  }
+
+  #[test]
+  fn test_read_entry_range() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+      .join("../node-resolver-core/test/fixture/pnp/.yarn/cache/foo-npm-1.0.0-abc123.zip");
+    let archive = ZipArchive::open(&OsFileSystem, &path).unwrap();
+    let contents = archive
+      .read(&RangeOnlyFileSystem, "node_modules/foo/main.js")
+      .unwrap();
+    assert!(String::from_utf8(contents)
+      .unwrap()
+      .contains("module.exports"));
+  }
+}